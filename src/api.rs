@@ -1,11 +1,13 @@
 use crate::{
-    errors::{UnpackError, UnpackResult},
+    errors::{KeySetError, KeySetResult, UnpackError, UnpackResult},
+    key_policy::KeyPolicy,
     keys::{self, AccessKey},
-    unpack, StdResult,
+    thumbprint, unpack, StdResult,
 };
 
 use std::collections::HashMap;
 
+use jsonwebtoken::{jwk, DecodingKey};
 use serde_json::Value;
 use ureq;
 
@@ -18,51 +20,187 @@ pub(crate) fn extract_latest_key_id(payload: &Value) -> UnpackResult<String> {
     .cloned()
 }
 
+pub(crate) fn extract_certificates(payload: &Value) -> HashMap<String, String> {
+    // get kid and cert string values at payload["public_cert"] and payload["public_certs"][*]
+    let mut cert_objs: Vec<&Value> = payload.get("public_cert").into_iter().collect();
+
+    if let Some(Value::Array(public_certs)) = payload.get("public_certs") {
+        cert_objs.extend(public_certs);
+    }
+
+    cert_objs
+        .into_iter()
+        .filter_map(|obj| {
+            let kid = obj.get("kid")?.as_str()?;
+            let cert = obj.get("cert")?.as_str()?;
+            Some((kid.to_string(), cert.to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn extract_first_key_id(payload: &Value) -> UnpackResult<String> {
+    // get string value at payload["keys"][0]["kid"]
+    let cert_objs = unpack::as_array(unpack::as_object_get_key(payload, "keys")?)?;
+    let first = cert_objs
+        .first()
+        .ok_or(UnpackError::empty_container("array"))?;
+
+    unpack::as_string(unpack::as_object_get_key(first, "kid")?).cloned()
+}
+
 pub(crate) fn extract_current_keys(payload: &Value) -> UnpackResult<keys::AccessKeyMap> {
+    let (map, _) = extract_current_keys_with_mode(payload, ParseMode::Strict)?;
+    Ok(map)
+}
+
+pub(crate) fn extract_current_keys_with_mode(
+    payload: &Value,
+    mode: ParseMode,
+) -> UnpackResult<(keys::AccessKeyMap, Vec<SkippedKey>)> {
     // get array value at payload["keys"]
     let cert_objs = unpack::as_array(unpack::as_object_get_key(payload, "keys")?)?;
 
-    if cert_objs.len() == 0 {
+    if cert_objs.is_empty() {
         return Err(UnpackError::empty_container("array"));
     }
 
     let mut map: keys::AccessKeyMap = HashMap::new();
+    let mut skipped: Vec<SkippedKey> = Vec::new();
 
     for val in cert_objs {
-        let obj = unpack::as_object(val)?;
-
-        // will need refactoring if/when cf aupports new key types
-        let access_key = keys::RsaAccessKey::new(
-            unpack::as_string(unpack::get_key(obj, "kid")?)?,
-            unpack::as_string(unpack::get_key(obj, "alg")?)?,
-            unpack::as_string(unpack::get_key(obj, "use")?)?,
-            unpack::as_string(unpack::get_key(obj, "e")?)?,
-            unpack::as_string(unpack::get_key(obj, "n")?)?,
-        );
+        match mode {
+            ParseMode::Strict => {
+                let access_key = extract_access_key(val)?;
+                check_access_key(access_key.as_ref())?;
+                map.insert(access_key.get_key_id(), access_key);
+            }
+            ParseMode::Lenient => {
+                let result = extract_access_key(val).and_then(|access_key| {
+                    check_access_key(access_key.as_ref())?;
+                    Ok(access_key)
+                });
+
+                match result {
+                    Ok(access_key) => {
+                        map.insert(access_key.get_key_id(), access_key);
+                    }
+                    Err(err) => skipped.push(SkippedKey::new(val, err)),
+                }
+            }
+        }
+    }
 
-        map.insert(access_key.get_key_id(), Box::new(access_key));
+    if map.is_empty() {
+        return Err(UnpackError::empty_container("key set"));
     }
 
-    Ok(map)
+    Ok((map, skipped))
+}
+
+/// Rejects keys that could never verify a CFZT JWT.
+fn check_access_key(access_key: &dyn AccessKey) -> UnpackResult<()> {
+    let jwk = access_key.get_jwk();
+    let key_id = access_key.get_key_id();
+
+    if jwk.common.public_key_use != Some(jwk::PublicKeyUse::Signature) {
+        return Err(UnpackError::invalid_key(&key_id, "key use is not 'sig'"));
+    }
+
+    if jwk.common.key_algorithm.is_none() {
+        return Err(UnpackError::invalid_key(&key_id, "unsupported alg"));
+    }
+
+    match DecodingKey::from_jwk(&jwk) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(UnpackError::invalid_key(&key_id, &err.to_string())),
+    }
+}
+
+fn extract_curve(obj: &unpack::JsonObject) -> UnpackResult<jwk::EllipticCurve> {
+    let curve = unpack::as_string(unpack::get_key(obj, "crv")?)?;
+    keys::parse_curve(curve).ok_or(UnpackError::unsupported_value("crv", curve))
+}
+
+pub(crate) fn extract_access_key(val: &Value) -> UnpackResult<Box<dyn AccessKey>> {
+    let obj = unpack::as_object(val)?;
+
+    let get_str =
+        |key: &str| -> UnpackResult<&String> { unpack::as_string(unpack::get_key(obj, key)?) };
+
+    // key sets published before kty was required are all RSA
+    let key_type = match obj.get("kty") {
+        Some(kty) => unpack::as_string(kty)?.as_str(),
+        None => "RSA",
+    };
+
+    let access_key: Box<dyn AccessKey> = match key_type {
+        "RSA" => Box::new(keys::RsaAccessKey::new(
+            get_str("kid")?,
+            get_str("alg")?,
+            get_str("use")?,
+            get_str("e")?,
+            get_str("n")?,
+        )),
+        "EC" => Box::new(keys::EcAccessKey::new(
+            get_str("kid")?,
+            get_str("alg")?,
+            get_str("use")?,
+            extract_curve(obj)?,
+            get_str("x")?,
+            get_str("y")?,
+        )),
+        "OKP" => Box::new(keys::OkpAccessKey::new(
+            get_str("kid")?,
+            get_str("alg")?,
+            get_str("use")?,
+            extract_curve(obj)?,
+            get_str("x")?,
+        )),
+        other => return Err(UnpackError::unsupported_value("kty", other)),
+    };
+
+    Ok(access_key)
 }
 
 fn get_team_key_uri(team_name: &str) -> String {
     format!("https://{team_name}.cloudflareaccess.com/cdn-cgi/access/certs")
 }
 
-fn get_json_payload(uri: &str) -> Result<Value, ureq::Error> {
+pub(crate) fn get_json_payload(uri: &str) -> StdResult<Value> {
     let payload = ureq::get(uri).call()?.into_json::<Value>()?;
 
     Ok(payload)
 }
 
-fn get_team_keys(team_name: &str) -> StdResult<(String, keys::AccessKeyMap)> {
-    let uri = get_team_key_uri(team_name);
-    let payload = get_json_payload(&uri)?;
-    Ok((
-        extract_latest_key_id(&payload)?,
-        extract_current_keys(&payload)?,
-    ))
+/// Controls how entries in the `keys` array that cannot be used are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Any entry that fails to parse, is not a signing key, or whose key material
+    /// is invalid fails the whole key set.
+    #[default]
+    Strict,
+    /// Entries that fail to parse, are not signing keys, or whose key material
+    /// is invalid are skipped and reported, as long as the latest key remains.
+    Lenient,
+}
+
+/// Describes an entry of the `keys` array skipped during lenient parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedKey {
+    pub key_id: Option<String>,
+    pub reason: String,
+}
+
+impl SkippedKey {
+    fn new(val: &Value, err: UnpackError) -> Self {
+        SkippedKey {
+            key_id: val
+                .get("kid")
+                .and_then(|kid| kid.as_str())
+                .map(|kid| kid.to_string()),
+            reason: err.to_string(),
+        }
+    }
 }
 
 /// Represents a set of trusted signing keys for a specific CFZT Team
@@ -70,28 +208,94 @@ pub struct TeamKeys {
     pub team_name: String,
     pub latest_key_id: String,
     pub keys: keys::AccessKeyMap,
+    pub skipped_keys: Vec<SkippedKey>,
+    /// PEM encoded certificates published alongside the keys, by key ID.
+    pub certificates: HashMap<String, String>,
 }
 
 impl TeamKeys {
-    fn new(team_name: &str, latest_key_id: &str, keys: keys::AccessKeyMap) -> Self {
+    pub(crate) fn new(
+        team_name: &str,
+        latest_key_id: &str,
+        keys: keys::AccessKeyMap,
+        skipped_keys: Vec<SkippedKey>,
+        certificates: HashMap<String, String>,
+    ) -> Self {
         TeamKeys {
             team_name: team_name.to_string(),
             latest_key_id: latest_key_id.to_string(),
             keys,
+            skipped_keys,
+            certificates,
         }
     }
 
     /// Attempts to load signing keys for a given team using a HTTP request.
     pub fn from_team_name(team_name: &str) -> StdResult<Self> {
-        let (latest_key_id, keys) = get_team_keys(team_name)?;
-        Ok(TeamKeys::new(team_name, &latest_key_id, keys))
+        TeamKeys::from_team_name_with_mode(team_name, ParseMode::Strict)
+    }
+
+    /// Attempts to load signing keys for a given team using a HTTP request,
+    /// handling unusable entries according to the given ParseMode.
+    pub fn from_team_name_with_mode(team_name: &str, mode: ParseMode) -> StdResult<Self> {
+        TeamKeys::from_url_with_mode(team_name, &get_team_key_uri(team_name), mode)
+    }
+
+    /// Attempts to load signing keys for a given team from a certs endpoint
+    /// other than the CF API, such as a local key mirror.
+    pub fn from_url(team_name: &str, uri: &str) -> StdResult<Self> {
+        TeamKeys::from_url_with_mode(team_name, uri, ParseMode::Strict)
+    }
+
+    /// Attempts to load signing keys for a given team from a certs endpoint
+    /// other than the CF API, handling unusable entries according to the given ParseMode.
+    pub fn from_url_with_mode(team_name: &str, uri: &str, mode: ParseMode) -> StdResult<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("cfzt.fetch_keys", team = team_name, url = uri).entered();
+
+        let result = get_json_payload(uri)
+            .and_then(|payload| TeamKeys::from_json_with_mode(team_name, payload, mode));
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(team_keys) => tracing::debug!(
+                latest_kid = %team_keys.latest_key_id,
+                keys = team_keys.keys.len(),
+                skipped_keys = team_keys.skipped_keys.len(),
+                "fetched keys"
+            ),
+            Err(err) => tracing::warn!(error = %err, "failed to fetch keys"),
+        }
+
+        result
     }
 
     // Attempts to load signing keys from a given serde_json::Value struct.
     pub fn from_json(team_name: &str, json_val: Value) -> StdResult<Self> {
+        TeamKeys::from_json_with_mode(team_name, json_val, ParseMode::Strict)
+    }
+
+    /// Attempts to load signing keys from a given serde_json::Value struct,
+    /// handling unusable entries according to the given ParseMode.
+    pub fn from_json_with_mode(
+        team_name: &str,
+        json_val: Value,
+        mode: ParseMode,
+    ) -> StdResult<Self> {
         let latest_key_id = extract_latest_key_id(&json_val)?;
-        let keys = extract_current_keys(&json_val)?;
-        Ok(TeamKeys::new(team_name, &latest_key_id, keys))
+        let (keys, skipped_keys) = extract_current_keys_with_mode(&json_val, mode)?;
+
+        if !keys.contains_key(&latest_key_id) {
+            return Err(UnpackError::missing_key(&latest_key_id).into());
+        }
+
+        Ok(TeamKeys::new(
+            team_name,
+            &latest_key_id,
+            keys,
+            skipped_keys,
+            extract_certificates(&json_val),
+        ))
     }
 
     // Attempts to load signing keys from a given JSON string slice.
@@ -99,15 +303,68 @@ impl TeamKeys {
         let json_val = serde_json::from_str(json_str)?;
         TeamKeys::from_json(team_name, json_val)
     }
+
+    /// Removes keys that violate a KeyPolicy, recording them as skipped keys.
+    /// Fails if the latest key violates the policy.
+    pub fn apply_key_policy(&mut self, policy: &KeyPolicy) -> KeySetResult<()> {
+        let mut rejected: Vec<(String, UnpackError)> = Vec::new();
+
+        for (key_id, access_key) in &self.keys {
+            if let Err(err) = policy.check_key(access_key.as_ref()) {
+                if *key_id == self.latest_key_id {
+                    return Err(KeySetError::latest_key_rejected(key_id, &err.to_string()));
+                }
+                rejected.push((key_id.clone(), err));
+            }
+        }
+
+        for (key_id, err) in rejected {
+            self.keys.remove(&key_id);
+            self.skipped_keys.push(SkippedKey {
+                key_id: Some(key_id),
+                reason: err.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the kid of every RSA key is one of its thumbprints, see `thumbprint::get_thumbprints`.
+    /// A mismatch indicates a tampered or corrupted key set: in Strict mode it fails the key set,
+    /// in Lenient mode the key is removed and recorded as a skipped key unless it is the latest key.
+    pub fn verify_key_ids(&mut self, mode: ParseMode) -> KeySetResult<()> {
+        let mut mismatched: Vec<String> = Vec::new();
+
+        for (key_id, access_key) in &self.keys {
+            if thumbprint::key_id_matches(access_key.as_ref()) == Some(false) {
+                if mode == ParseMode::Strict || *key_id == self.latest_key_id {
+                    return Err(KeySetError::key_id_mismatch(key_id));
+                }
+                mismatched.push(key_id.clone());
+            }
+        }
+
+        for key_id in mismatched {
+            self.keys.remove(&key_id);
+            self.skipped_keys.push(SkippedKey {
+                key_id: Some(key_id.clone()),
+                reason: KeySetError::key_id_mismatch(&key_id).to_string(),
+            });
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use jsonwebtoken::jwk;
-    use serde_json;
+    use crate::{mock_server::MockCertsServer, testing::MockIssuer};
+    use serde_json::{self, json};
 
     const DUMMY_PAYLOAD: &str = include_str!("../test_data/dummy_signing_keys.json");
+    const SAMPLE_PAYLOAD: &str = include_str!("../test_data/sample_signing_keys.json");
+    const EC_OKP_PAYLOAD: &str = include_str!("../test_data/mock_ec_okp_signing_keys.json");
 
     const EXPECTED_LATEST_KEY_ID: &str = "foo";
     const EXPECTED_LATEST_KEY_CONTENT: &str = "bar";
@@ -120,7 +377,7 @@ mod tests {
         serde_json::from_str(DUMMY_PAYLOAD).unwrap()
     }
 
-    fn assert_access_key_content(key: &Box<dyn AccessKey>, expect: &str) {
+    fn assert_access_key_content(key: &dyn AccessKey, expect: &str) {
         match key.get_jwk().algorithm {
             jwk::AlgorithmParameters::RSA(params) => {
                 assert_eq!(params.e, expect);
@@ -145,24 +402,183 @@ mod tests {
     #[test]
     fn test_unpack_current_keys() {
         let payload = get_payload_value();
-        let actual_keys = extract_current_keys(&payload).unwrap();
 
-        assert!(actual_keys.contains_key(EXPECTED_LATEST_KEY_ID));
-        assert!(actual_keys.contains_key(EXPECTED_ADDITIONAL_KEY_ID));
+        // the dummy key material cannot verify tokens, so the key set is rejected
+        assert!(extract_current_keys(&payload).is_err());
 
-        let latest_key = actual_keys.get(EXPECTED_LATEST_KEY_ID).unwrap();
-        let additional_key = actual_keys.get(EXPECTED_ADDITIONAL_KEY_ID).unwrap();
+        let latest_key = extract_access_key(&payload["keys"][0]).unwrap();
+        let additional_key = extract_access_key(&payload["keys"][1]).unwrap();
 
         assert_eq!(latest_key.get_key_id(), EXPECTED_LATEST_KEY_ID);
         assert_eq!(additional_key.get_key_id(), EXPECTED_ADDITIONAL_KEY_ID);
 
-        assert_access_key_content(latest_key, EXPECTED_LATEST_KEY_CONTENT);
-        assert_access_key_content(additional_key, EXPECTED_ADDITIONAL_KEY_CONTENT);
+        assert_access_key_content(latest_key.as_ref(), EXPECTED_LATEST_KEY_CONTENT);
+        assert_access_key_content(additional_key.as_ref(), EXPECTED_ADDITIONAL_KEY_CONTENT);
+    }
+
+    #[test]
+    fn test_unpack_certificates() {
+        let payload = get_payload_value();
+        let certificates = extract_certificates(&payload);
+
+        assert_eq!(certificates.len(), 2);
+        assert_eq!(
+            certificates[EXPECTED_LATEST_KEY_ID],
+            EXPECTED_LATEST_KEY_CONTENT
+        );
+        assert_eq!(
+            certificates[EXPECTED_ADDITIONAL_KEY_ID],
+            EXPECTED_ADDITIONAL_KEY_CONTENT
+        );
+
+        let payload: Value = serde_json::from_str(EC_OKP_PAYLOAD).unwrap();
+        assert!(extract_certificates(&payload).is_empty());
+    }
+
+    #[test]
+    fn test_unpack_ec_okp_keys() {
+        let payload: Value = serde_json::from_str(EC_OKP_PAYLOAD).unwrap();
+        let actual_keys = extract_current_keys(&payload).unwrap();
+
+        assert_eq!(actual_keys.len(), 3);
+
+        match actual_keys.get("mock-es384").unwrap().get_jwk().algorithm {
+            jwk::AlgorithmParameters::EllipticCurve(params) => {
+                assert_eq!(params.curve, jwk::EllipticCurve::P384)
+            }
+            _ => panic!("incorrect AlgorithmParameters for kid 'mock-es384'"),
+        }
+
+        match actual_keys.get("mock-eddsa").unwrap().get_jwk().algorithm {
+            jwk::AlgorithmParameters::OctetKeyPair(params) => {
+                assert_eq!(params.curve, jwk::EllipticCurve::Ed25519)
+            }
+            _ => panic!("incorrect AlgorithmParameters for kid 'mock-eddsa'"),
+        }
+    }
+
+    #[test]
+    fn test_unpack_unsupported_key() {
+        let unknown_kty =
+            json!({"kid": "foo", "kty": "oct", "alg": "HS256", "use": "sig", "k": "bar"});
+        assert!(extract_access_key(&unknown_kty).is_err());
+
+        let unknown_crv = json!({"kid": "foo", "kty": "EC", "alg": "ES256", "use": "sig", "crv": "secp256k1", "x": "bar", "y": "bar"});
+        assert!(extract_access_key(&unknown_crv).is_err());
+    }
+
+    fn get_mixed_payload() -> Value {
+        let mut payload: Value = serde_json::from_str(SAMPLE_PAYLOAD).unwrap();
+        let keys = payload["keys"].as_array_mut().unwrap();
+        keys.push(
+            json!({"kid": "unknown", "kty": "oct", "alg": "HS256", "use": "sig", "k": "bar"}),
+        );
+        keys.push(json!({"kid": "enc", "kty": "RSA", "alg": "RSA-OAEP", "use": "enc", "e": "AQAB", "n": "bar"}));
+        keys.push(json!({"kid": "bad-modulus", "kty": "RSA", "alg": "RS256", "use": "sig", "e": "AQAB", "n": "!!"}));
+        payload
+    }
+
+    #[test]
+    fn test_unpack_current_keys_strict() {
+        let result = extract_current_keys_with_mode(&get_mixed_payload(), ParseMode::Strict);
+        assert!(result.is_err());
+
+        // an undecodable modulus fails the key set rather than the first token using it
+        let mut payload: Value = serde_json::from_str(SAMPLE_PAYLOAD).unwrap();
+        payload["keys"][0]["n"] = json!("!!");
+        assert!(extract_current_keys_with_mode(&payload, ParseMode::Strict).is_err());
+        assert!(TeamKeys::from_json(TEST_TEAM, payload).is_err());
+    }
+
+    #[test]
+    fn test_unpack_current_keys_lenient() {
+        let (keys, skipped) =
+            extract_current_keys_with_mode(&get_mixed_payload(), ParseMode::Lenient).unwrap();
+
+        assert_eq!(keys.len(), 2);
+
+        let skipped_ids: Vec<String> = skipped.into_iter().filter_map(|key| key.key_id).collect();
+        assert_eq!(skipped_ids, vec!["unknown", "enc", "bad-modulus"]);
+    }
+
+    #[test]
+    fn test_team_keys_lenient_missing_latest_key() {
+        let mut payload = get_mixed_payload();
+        payload["public_cert"]["kid"] = json!("bad-modulus");
+
+        let result = TeamKeys::from_json_with_mode(TEST_TEAM, payload, ParseMode::Lenient);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_apply_key_policy() {
+        let mut payload: Value = serde_json::from_str(SAMPLE_PAYLOAD).unwrap();
+        payload["keys"][0]["alg"] = json!("RS512");
+        let additional_key_id = payload["keys"][0]["kid"].as_str().unwrap().to_string();
+
+        let mut team_keys = TeamKeys::from_json(TEST_TEAM, payload).unwrap();
+        let policy = KeyPolicy::default().with_allowed_algorithms(&[jwk::KeyAlgorithm::RS256]);
+        team_keys.apply_key_policy(&policy).unwrap();
+
+        assert_eq!(team_keys.keys.len(), 1);
+        assert_eq!(team_keys.skipped_keys.len(), 1);
+        assert_eq!(team_keys.skipped_keys[0].key_id, Some(additional_key_id));
+
+        let mut team_keys = TeamKeys::from_str(TEST_TEAM, SAMPLE_PAYLOAD).unwrap();
+        let policy = KeyPolicy::default().with_min_rsa_bits(4096);
+        assert!(team_keys.apply_key_policy(&policy).is_err());
+    }
+
+    #[test]
+    fn test_verify_key_ids() {
+        let mut team_keys = TeamKeys::from_str(TEST_TEAM, SAMPLE_PAYLOAD).unwrap();
+        assert!(team_keys.verify_key_ids(ParseMode::Strict).is_ok());
+        assert_eq!(team_keys.keys.len(), 2);
+
+        // swap the moduli so each kid no longer matches its key
+        let mut payload: Value = serde_json::from_str(SAMPLE_PAYLOAD).unwrap();
+        let modulus = payload["keys"][0]["n"].clone();
+        payload["keys"][0]["n"] = payload["keys"][1]["n"].clone();
+        payload["keys"][1]["n"] = modulus;
+        let mut team_keys = TeamKeys::from_json(TEST_TEAM, payload.clone()).unwrap();
+        assert!(team_keys.verify_key_ids(ParseMode::Strict).is_err());
+        let mut team_keys = TeamKeys::from_json(TEST_TEAM, payload).unwrap();
+        assert!(team_keys.verify_key_ids(ParseMode::Lenient).is_err());
+
+        // a mismatched key other than the latest key is skipped in lenient mode
+        let mut payload: Value = serde_json::from_str(SAMPLE_PAYLOAD).unwrap();
+        let latest_key_id = payload["public_cert"]["kid"].clone();
+        let index = if payload["keys"][0]["kid"] == latest_key_id {
+            1
+        } else {
+            0
+        };
+        payload["keys"][index]["kid"] = json!("tampered");
+        let mut team_keys = TeamKeys::from_json(TEST_TEAM, payload.clone()).unwrap();
+        assert!(team_keys.verify_key_ids(ParseMode::Strict).is_err());
+        let mut team_keys = TeamKeys::from_json(TEST_TEAM, payload).unwrap();
+        assert!(team_keys.verify_key_ids(ParseMode::Lenient).is_ok());
+        assert_eq!(team_keys.keys.len(), 1);
+        assert_eq!(team_keys.skipped_keys.len(), 1);
+        assert_eq!(
+            team_keys.skipped_keys[0].key_id,
+            Some("tampered".to_string())
+        );
+
+        let mut team_keys = TeamKeys::from_str(TEST_TEAM, EC_OKP_PAYLOAD).unwrap();
+        assert!(team_keys.verify_key_ids(ParseMode::Strict).is_ok());
     }
 
     #[test]
     fn test_get_team_keys() {
-        let result = get_team_keys(TEST_TEAM);
+        assert_eq!(
+            get_team_key_uri(TEST_TEAM),
+            "https://example.cloudflareaccess.com/cdn-cgi/access/certs"
+        );
+
+        let server = MockCertsServer::start();
+        server.publish(&MockIssuer::new(TEST_TEAM));
+        let result = TeamKeys::from_url(TEST_TEAM, &server.get_certs_url(TEST_TEAM));
         assert!(result.is_ok());
     }
 }
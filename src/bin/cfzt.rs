@@ -0,0 +1,313 @@
+use rust_cfzt_validator::{
+    api::TeamKeys,
+    diff::KeySetDiff,
+    inspect::{self, TokenReport},
+    StdResult, TeamValidator,
+};
+
+use std::{
+    fs,
+    io::{self, Read},
+    process::ExitCode,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::{Args, Parser, Subcommand};
+use serde_json::json;
+
+/// Inspects Cloudflare Zero Trust Application Tokens and signing keys.
+#[derive(Parser)]
+#[command(name = "cfzt", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the header and claims of a token without verifying it
+    Decode {
+        /// The token, or '-' to read it from stdin
+        token: String,
+    },
+    /// Validates a token against a team and prints a breakdown of each check
+    Validate {
+        /// The token, or '-' to read it from stdin
+        token: String,
+        #[command(flatten)]
+        keys: KeySource,
+        /// An expected audience (application AUD tag), may be repeated
+        #[arg(long = "aud", required = true)]
+        audiences: Vec<String>,
+    },
+    /// Fetches the keys of a team and prints their kids and algorithms
+    Fetch {
+        #[command(flatten)]
+        live: LiveSource,
+        /// Also write the keys to a snapshot file in the certs JSON format
+        #[arg(long, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Compares a snapshot to another snapshot, or to the live keys of a team
+    Diff {
+        /// The previous snapshot file
+        previous: String,
+        /// The current snapshot file, the live keys of --team if omitted
+        current: Option<String>,
+        /// The Zero Trust team name, to compare against live keys
+        #[arg(long, required_unless_present = "current")]
+        team: Option<String>,
+        /// Fetch live keys from this certs endpoint instead of the CF API
+        #[arg(long, value_name = "URL")]
+        certs_url: Option<String>,
+    },
+    /// Polls the keys of a team and reports rotations as they happen
+    Watch {
+        #[command(flatten)]
+        live: LiveSource,
+        /// The interval between polls
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value_t = 60,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        interval: u64,
+    },
+}
+
+#[derive(Args)]
+struct LiveSource {
+    /// The Zero Trust team name
+    #[arg(long)]
+    team: String,
+    /// Fetch keys from this certs endpoint instead of the CF API
+    #[arg(long, value_name = "URL")]
+    certs_url: Option<String>,
+}
+
+impl LiveSource {
+    fn load(&self) -> StdResult<TeamKeys> {
+        fetch_keys(&self.team, self.certs_url.as_deref())
+    }
+}
+
+fn fetch_keys(team_name: &str, certs_url: Option<&str>) -> StdResult<TeamKeys> {
+    match certs_url {
+        Some(url) => TeamKeys::from_url(team_name, url),
+        None => TeamKeys::from_team_name(team_name),
+    }
+}
+
+fn load_snapshot(team_name: &str, path: &str) -> StdResult<TeamKeys> {
+    TeamKeys::from_str(team_name, &fs::read_to_string(path)?)
+}
+
+#[derive(Args)]
+struct KeySource {
+    /// The Zero Trust team name
+    #[arg(long)]
+    team: String,
+    /// Load keys from a certs JSON file instead of the CF API
+    #[arg(long, value_name = "FILE")]
+    certs: Option<String>,
+}
+
+impl KeySource {
+    fn load(&self) -> StdResult<TeamKeys> {
+        match &self.certs {
+            Some(path) => load_snapshot(&self.team, path),
+            None => TeamKeys::from_team_name(&self.team),
+        }
+    }
+}
+
+fn read_token(token: &str) -> StdResult<String> {
+    match token {
+        "-" => {
+            let mut buf = String::new();
+            io::stdin().read_to_string(&mut buf)?;
+            Ok(buf.trim().to_string())
+        }
+        _ => Ok(token.trim().to_string()),
+    }
+}
+
+fn decode(token: &str) -> StdResult<bool> {
+    let token_data = inspect::decode_token(&read_token(token)?)?;
+    let output = json!({
+        "header": token_data.header,
+        "claims": token_data.claims,
+    });
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(true)
+}
+
+fn print_report(report: &TokenReport) {
+    println!(
+        "alg: {:?}, kid: {}",
+        report.header.alg,
+        report.header.kid.as_deref().unwrap_or("<none>")
+    );
+
+    for check in &report.checks {
+        println!("{check}");
+    }
+
+    match &report.result {
+        Ok(_) => println!("\nresult: PASS"),
+        Err(err) => println!("\nresult: FAIL ({err})"),
+    }
+
+    if let Some(app_token) = report.get_application_token() {
+        println!("\nemail:   {}", app_token.email);
+        println!("sub:     {}", app_token.sub);
+        println!("iss:     {}", app_token.iss);
+        println!("country: {}", app_token.country);
+        println!("iat:     {}", app_token.iat);
+        println!("nbf:     {}", app_token.nbf);
+        println!("exp:     {}", app_token.exp);
+        println!("custom:  {}", json!(app_token.custom));
+    }
+}
+
+fn validate(token: &str, keys: &KeySource, audiences: &[String]) -> StdResult<bool> {
+    let token = read_token(token)?;
+    let validator = TeamValidator::from_team_keys(keys.load()?);
+    let audiences: Vec<&str> = audiences.iter().map(|aud| aud.as_str()).collect();
+
+    let report = inspect::inspect_token(&validator, &token, &audiences)?;
+    print_report(&report);
+    Ok(report.passed())
+}
+
+fn print_keys(team_keys: &TeamKeys) {
+    let jwks = team_keys.to_jwks();
+
+    for key in jwks["keys"].as_array().into_iter().flatten() {
+        let marker = match key["kid"] == jwks["latest_kid"] {
+            true => "*",
+            false => " ",
+        };
+        println!(
+            "{marker} {}  {}  {}  {}",
+            key["kid"].as_str().unwrap_or_default(),
+            key["alg"].as_str().unwrap_or("-"),
+            key["kty"].as_str().unwrap_or("-"),
+            key["status"].as_str().unwrap_or_default(),
+        );
+    }
+
+    for skipped_key in &team_keys.skipped_keys {
+        let key_id = skipped_key.key_id.as_deref().unwrap_or("<none>");
+        println!("! {key_id}  skipped: {}", skipped_key.reason);
+    }
+}
+
+fn fetch(live: &LiveSource, output: Option<&str>) -> StdResult<bool> {
+    let team_keys = live.load()?;
+    print_keys(&team_keys);
+
+    if let Some(path) = output {
+        fs::write(
+            path,
+            serde_json::to_string_pretty(&team_keys.to_certs_json())?,
+        )?;
+        eprintln!("wrote snapshot to {path}");
+    }
+    Ok(true)
+}
+
+fn diff(
+    previous: &str,
+    current: Option<&str>,
+    team_name: Option<&str>,
+    certs_url: Option<&str>,
+) -> StdResult<bool> {
+    let team_name = team_name.unwrap_or("snapshot");
+    let previous = load_snapshot(team_name, previous)?;
+    let current = match current {
+        Some(path) => load_snapshot(team_name, path)?,
+        None => fetch_keys(team_name, certs_url)?,
+    };
+
+    let key_set_diff = KeySetDiff::between(&previous, &current);
+    if !key_set_diff.is_empty() {
+        println!("{key_set_diff}");
+    }
+    Ok(key_set_diff.is_empty())
+}
+
+fn get_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn watch(live: &LiveSource, interval: u64) -> StdResult<bool> {
+    let mut previous = live.load()?;
+    println!("[{}] watching team '{}'", get_timestamp(), live.team);
+    print_keys(&previous);
+
+    loop {
+        thread::sleep(Duration::from_secs(interval));
+
+        let current = match live.load() {
+            Ok(current) => current,
+            Err(err) => {
+                eprintln!("[{}] failed to fetch keys: {err}", get_timestamp());
+                continue;
+            }
+        };
+
+        let key_set_diff = KeySetDiff::between(&previous, &current);
+        if key_set_diff.is_empty() {
+            continue;
+        }
+
+        let event = match (key_set_diff.changed.is_empty(), key_set_diff.is_rotation()) {
+            (false, _) => "key material changed",
+            (true, true) => "key rotation",
+            (true, false) => "key set changed",
+        };
+        println!("[{}] {event}\n{key_set_diff}", get_timestamp());
+        previous = current;
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match &cli.command {
+        Command::Decode { token } => decode(token),
+        Command::Validate {
+            token,
+            keys,
+            audiences,
+        } => validate(token, keys, audiences),
+        Command::Fetch { live, output } => fetch(live, output.as_deref()),
+        Command::Diff {
+            previous,
+            current,
+            team,
+            certs_url,
+        } => diff(
+            previous,
+            current.as_deref(),
+            team.as_deref(),
+            certs_url.as_deref(),
+        ),
+        Command::Watch { live, interval } => watch(live, *interval),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
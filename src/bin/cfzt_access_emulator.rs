@@ -0,0 +1,62 @@
+use rust_cfzt_validator::emulator::AccessEmulator;
+
+use std::process::ExitCode;
+
+use clap::Parser;
+use tiny_http::Server;
+
+/// Emulates Cloudflare Access in front of a local application, for development only.
+///
+/// Signs tokens with generated keys, which are served at /cdn-cgi/access/certs.
+#[derive(Parser)]
+#[command(name = "cfzt-access-emulator", version)]
+struct Cli {
+    /// The address to listen on
+    #[arg(long, default_value = "127.0.0.1:8787")]
+    listen: String,
+    /// The base URL of the local application
+    #[arg(long, default_value = "http://127.0.0.1:3000")]
+    upstream: String,
+    /// The Zero Trust team name used in the iss claim
+    #[arg(long, default_value = "dev")]
+    team: String,
+    /// The application audience (AUD tag) used in the aud claim
+    #[arg(long, default_value = "dev-audience")]
+    aud: String,
+    /// An email offered on the login page, may be repeated
+    #[arg(
+        long = "identity",
+        value_name = "EMAIL",
+        default_value = "dev@localhost"
+    )]
+    identities: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let identities: Vec<&str> = cli.identities.iter().map(|email| email.as_str()).collect();
+
+    let emulator =
+        AccessEmulator::new(&cli.team, &cli.aud, &cli.upstream).with_identities(&identities);
+
+    let server = match Server::http(&cli.listen) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("error: failed to listen on {}: {err}", cli.listen);
+            return ExitCode::from(2);
+        }
+    };
+
+    eprintln!("WARNING: this emulator issues tokens for any listed identity, never expose it");
+    eprintln!(
+        "proxying http://{} to {} as team '{}', audience '{}'",
+        cli.listen, cli.upstream, cli.team, cli.aud
+    );
+    eprintln!(
+        "validate tokens with TeamValidator::from_certs_url(\"{}\", \"http://{}/cdn-cgi/access/certs\")",
+        cli.team, cli.listen
+    );
+
+    emulator.serve(&server);
+    ExitCode::SUCCESS
+}
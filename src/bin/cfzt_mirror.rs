@@ -0,0 +1,76 @@
+use rust_cfzt_validator::{api::ParseMode, mirror::KeyMirror};
+
+use std::{process, sync::Arc, thread, time::Duration};
+
+use clap::Parser;
+use tiny_http::Server;
+
+/// Mirrors the signing keys of Zero Trust teams for validators without CF API access.
+///
+/// Syncs the signing keys of each team and serves them at
+/// /<team>/cdn-cgi/access/certs in the Cloudflare certs format.
+#[derive(Parser)]
+#[command(name = "cfzt-mirror", version)]
+struct Cli {
+    /// A Zero Trust team to mirror, may be repeated
+    #[arg(long = "team", value_name = "NAME", required = true)]
+    team_names: Vec<String>,
+    /// The address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// The interval between syncs
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 300,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    interval: u64,
+    /// Sync from this URL instead of the CF API, '{team_name}' is replaced with the team name
+    #[arg(long, value_name = "URL")]
+    upstream: Option<String>,
+    /// Skip unusable keys instead of rejecting the key set
+    #[arg(long)]
+    lenient: bool,
+}
+
+fn sync(mirror: &KeyMirror) {
+    for team_name in mirror.get_team_names() {
+        match mirror.sync_team(&team_name) {
+            Ok(()) => eprintln!("synced keys for team '{team_name}'"),
+            Err(err) => eprintln!("failed to sync keys for team '{team_name}': {err}"),
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let team_names: Vec<&str> = cli.team_names.iter().map(|name| name.as_str()).collect();
+    let parse_mode = match cli.lenient {
+        true => ParseMode::Lenient,
+        false => ParseMode::Strict,
+    };
+
+    let mut mirror = KeyMirror::new(&team_names).with_parse_mode(parse_mode);
+    if let Some(upstream) = &cli.upstream {
+        mirror = mirror.with_upstream(upstream);
+    }
+    let mirror = Arc::new(mirror);
+
+    sync(&mirror);
+
+    let syncing = mirror.clone();
+    let interval = Duration::from_secs(cli.interval);
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        sync(&syncing);
+    });
+
+    let server = Server::http(&cli.listen).unwrap_or_else(|err| {
+        eprintln!("failed to listen on {}: {err}", cli.listen);
+        process::exit(1)
+    });
+
+    eprintln!("serving {} team(s) on {}", team_names.len(), cli.listen);
+    mirror.serve(&server);
+}
@@ -0,0 +1,253 @@
+use crate::{
+    api::TeamKeys,
+    clock::Clock,
+    der::{decode_b64, strip_leading_zeros},
+    errors::{KeySetError, KeySetResult},
+    keys::AccessKey,
+};
+
+use jsonwebtoken::jwk;
+use x509_parser::{pem::parse_x509_pem, public_key::PublicKey};
+
+/// Controls how keys whose certificate is outside its validity window are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CertificatePolicy {
+    /// Expired and not yet valid certificates are reported but their keys remain trusted.
+    #[default]
+    Flag,
+    /// Keys with an expired certificate are removed from the key set.
+    RejectExpired,
+    /// Keys with an expired or not yet valid certificate are removed from the key set.
+    RejectInvalid,
+}
+
+impl CertificatePolicy {
+    fn get_rejection_reason(&self, cert: &KeyCertificate) -> Option<&'static str> {
+        match self {
+            CertificatePolicy::Flag => None,
+            _ if cert.expired => Some("certificate has expired"),
+            CertificatePolicy::RejectInvalid if cert.not_yet_valid => {
+                Some("certificate is not yet valid")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A certificate published in `public_cert`/`public_certs`, parsed and
+/// cross-checked against the JWK with the same key ID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyCertificate {
+    pub key_id: String,
+    pub subject: String,
+    pub issuer: String,
+    /// Start of the validity window, in seconds since the unix epoch.
+    pub not_before: i64,
+    /// End of the validity window, in seconds since the unix epoch.
+    pub not_after: i64,
+    /// True if the certificate was past the end of its validity window when verified.
+    pub expired: bool,
+    /// True if the certificate was before the start of its validity window when verified.
+    pub not_yet_valid: bool,
+}
+
+impl KeyCertificate {
+    /// Parses a PEM encoded certificate and checks that its public key
+    /// matches the given AccessKey.
+    pub fn verify(access_key: &dyn AccessKey, pem: &str, now: i64) -> KeySetResult<Self> {
+        let key_id = access_key.get_key_id();
+        let parse_failure = || KeySetError::certificate_parse_failure(&key_id);
+
+        let (_, pem) = parse_x509_pem(pem.as_bytes()).map_err(|_| parse_failure())?;
+        let cert = pem.parse_x509().map_err(|_| parse_failure())?;
+        let public_key = cert.public_key().parsed().map_err(|_| parse_failure())?;
+
+        if !public_key_matches(&access_key.get_jwk(), &public_key) {
+            return Err(KeySetError::certificate_key_mismatch(&key_id));
+        }
+
+        let not_before = cert.validity().not_before.timestamp();
+        let not_after = cert.validity().not_after.timestamp();
+
+        Ok(KeyCertificate {
+            key_id,
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            not_before,
+            not_after,
+            expired: now > not_after,
+            not_yet_valid: now < not_before,
+        })
+    }
+}
+
+fn public_key_matches(jwk: &jwk::Jwk, public_key: &PublicKey) -> bool {
+    match (&jwk.algorithm, public_key) {
+        (jwk::AlgorithmParameters::RSA(params), PublicKey::RSA(rsa)) => {
+            match (decode_b64(&params.n), decode_b64(&params.e)) {
+                (Some(n), Some(e)) => {
+                    strip_leading_zeros(&n) == strip_leading_zeros(rsa.modulus)
+                        && strip_leading_zeros(&e) == strip_leading_zeros(rsa.exponent)
+                }
+                _ => false,
+            }
+        }
+        (jwk::AlgorithmParameters::EllipticCurve(params), PublicKey::EC(point)) => {
+            match (decode_b64(&params.x), decode_b64(&params.y)) {
+                // uncompressed SEC1 point: 0x04 || x || y
+                (Some(x), Some(y)) => point.data() == [&[0x04], x.as_slice(), &y].concat(),
+                _ => false,
+            }
+        }
+        (jwk::AlgorithmParameters::OctetKeyPair(params), PublicKey::Unknown(raw)) => {
+            decode_b64(&params.x).is_some_and(|x| x.as_slice() == *raw)
+        }
+        _ => false,
+    }
+}
+
+impl TeamKeys {
+    /// Parses the certificates published alongside the keys and checks that each
+    /// matches the JWK with the same key ID. A mismatch or unparseable certificate
+    /// fails the whole key set. Keys without a certificate are left untouched.
+    /// Validity windows are evaluated at the current time of the given Clock.
+    pub fn verify_certificates(
+        &mut self,
+        policy: CertificatePolicy,
+        clock: &dyn Clock,
+    ) -> KeySetResult<Vec<KeyCertificate>> {
+        self.verify_certificates_at(policy, clock.timestamp() as i64)
+    }
+
+    /// As `verify_certificates`, evaluating validity windows at the given unix time.
+    pub fn verify_certificates_at(
+        &mut self,
+        policy: CertificatePolicy,
+        now: i64,
+    ) -> KeySetResult<Vec<KeyCertificate>> {
+        let mut verified: Vec<KeyCertificate> = Vec::new();
+
+        for (key_id, pem) in &self.certificates {
+            if let Some(access_key) = self.keys.get(key_id) {
+                verified.push(KeyCertificate::verify(access_key.as_ref(), pem, now)?);
+            }
+        }
+
+        let rejected: Vec<(&String, &str)> = verified
+            .iter()
+            .filter_map(|cert| Some((&cert.key_id, policy.get_rejection_reason(cert)?)))
+            .collect();
+
+        if let Some((key_id, reason)) = rejected
+            .iter()
+            .find(|(key_id, _)| **key_id == self.latest_key_id)
+        {
+            return Err(KeySetError::latest_key_rejected(key_id, reason));
+        }
+
+        for (key_id, _) in rejected {
+            self.keys.remove(key_id);
+        }
+
+        verified.sort_by(|a, b| a.key_id.cmp(&b.key_id));
+        Ok(verified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use serde_json::Value;
+
+    const TEAM_NAME: &str = "molten";
+    const STATIC_KEYS: &str = include_str!("../test_data/sample_signing_keys.json");
+    const LATEST_KEY_ID: &str = "a5ea8bd1b94cadf2a5f0f47dad188e6aafbcd28eeab2e71b11ddd96d9cc28c69";
+    const ADDITIONAL_KEY_ID: &str =
+        "1112fda21ace0ef9f8be527697e87970566d4e4dfab130d9a9d7a0748d3da8dd";
+
+    // 2024-06-10, 2025-07-01
+    const WITHIN_VALIDITY: i64 = 1717979639;
+    const AFTER_EXPIRY: i64 = 1751328000;
+    // 2023-11-14
+    const BEFORE_VALIDITY: i64 = 1700000000;
+
+    fn get_team_keys() -> TeamKeys {
+        TeamKeys::from_str(TEAM_NAME, STATIC_KEYS).unwrap()
+    }
+
+    #[test]
+    fn test_verify_certificates() {
+        let mut team_keys = get_team_keys();
+        let clock = FakeClock::new(WITHIN_VALIDITY as u64);
+        let certs = team_keys
+            .verify_certificates(CertificatePolicy::RejectExpired, &clock)
+            .unwrap();
+
+        assert_eq!(certs.len(), 2);
+        assert_eq!(certs[0].key_id, ADDITIONAL_KEY_ID);
+        assert_eq!(certs[1].key_id, LATEST_KEY_ID);
+        assert!(certs
+            .iter()
+            .all(|cert| !cert.expired && !cert.not_yet_valid));
+        assert_eq!(certs[1].not_before, 1717779032);
+        assert_eq!(certs[1].not_after, 1750524632);
+        assert!(certs[1].subject.contains("CN=cloudflareaccess.com"));
+        assert_eq!(team_keys.keys.len(), 2);
+    }
+
+    #[test]
+    fn test_verify_certificates_expired() {
+        let mut team_keys = get_team_keys();
+        let certs = team_keys
+            .verify_certificates_at(CertificatePolicy::Flag, AFTER_EXPIRY)
+            .unwrap();
+
+        assert!(certs.iter().all(|cert| cert.expired));
+        assert_eq!(team_keys.keys.len(), 2);
+
+        let result =
+            get_team_keys().verify_certificates_at(CertificatePolicy::RejectExpired, AFTER_EXPIRY);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_verify_certificates_not_yet_valid() {
+        let mut team_keys = get_team_keys();
+        let certs = team_keys
+            .verify_certificates_at(CertificatePolicy::RejectExpired, BEFORE_VALIDITY)
+            .unwrap();
+
+        assert!(certs.iter().all(|cert| cert.not_yet_valid && !cert.expired));
+        assert_eq!(team_keys.keys.len(), 2);
+
+        let result = get_team_keys()
+            .verify_certificates_at(CertificatePolicy::RejectInvalid, BEFORE_VALIDITY);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_verify_certificates_reject_expired_additional_key() {
+        let mut team_keys = get_team_keys();
+        // keep only the additional key's certificate, then expire it
+        team_keys.certificates.remove(LATEST_KEY_ID);
+
+        team_keys
+            .verify_certificates_at(CertificatePolicy::RejectExpired, AFTER_EXPIRY)
+            .unwrap();
+
+        assert!(team_keys.keys.contains_key(LATEST_KEY_ID));
+        assert!(!team_keys.keys.contains_key(ADDITIONAL_KEY_ID));
+    }
+
+    #[test]
+    fn test_verify_certificates_key_mismatch() {
+        let mut payload: Value = serde_json::from_str(STATIC_KEYS).unwrap();
+        let latest_cert = payload["public_cert"]["cert"].clone();
+        payload["public_certs"][0]["cert"] = latest_cert;
+
+        let mut team_keys = TeamKeys::from_json(TEAM_NAME, payload).unwrap();
+        let result = team_keys.verify_certificates_at(CertificatePolicy::Flag, WITHIN_VALIDITY);
+        assert!(result.is_err());
+    }
+}
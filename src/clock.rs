@@ -0,0 +1,77 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A source of the current time, used for `exp`/`nbf` checks, key staleness and refresh scheduling.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+
+    /// Returns the current time as a unix timestamp in seconds, as used by JWT claims.
+    fn timestamp(&self) -> u64 {
+        self.now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    }
+}
+
+/// The Clock used by default, reading the system time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A Clock that only moves when told to, so that expiry, leeway and grace periods
+/// can be tested deterministically. Share it with an `Arc` to keep control of it.
+#[derive(Debug)]
+pub struct FakeClock {
+    now: Mutex<SystemTime>,
+}
+
+impl FakeClock {
+    /// Constructs a FakeClock frozen at a unix timestamp in seconds.
+    pub fn new(timestamp: u64) -> Self {
+        FakeClock {
+            now: Mutex::new(UNIX_EPOCH + Duration::from_secs(timestamp)),
+        }
+    }
+
+    /// Moves the clock to a unix timestamp in seconds.
+    pub fn set(&self, timestamp: u64) {
+        *self.now.lock().unwrap() = UNIX_EPOCH + Duration::from_secs(timestamp);
+    }
+
+    /// Moves the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock() {
+        let clock = FakeClock::new(1717979639);
+        assert_eq!(clock.timestamp(), 1717979639);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(clock.timestamp(), 1717979640);
+
+        clock.set(0);
+        assert_eq!(clock.now(), UNIX_EPOCH);
+        assert!(SystemClock.timestamp() > 1717979639);
+    }
+}
@@ -0,0 +1,160 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use jsonwebtoken::jwk;
+
+// AlgorithmIdentifier { rsaEncryption, NULL }
+const RSA_ALGORITHM_IDENTIFIER: [u8; 15] = [
+    0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00,
+];
+
+// OID id-ecPublicKey
+const EC_PUBLIC_KEY_OID: [u8; 9] = [0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+
+// AlgorithmIdentifier { id-Ed25519 }
+const ED25519_ALGORITHM_IDENTIFIER: [u8; 7] = [0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70];
+
+const PEM_LINE_LENGTH: usize = 64;
+
+fn get_curve_oid(curve: &jwk::EllipticCurve) -> Option<&'static [u8]> {
+    match curve {
+        jwk::EllipticCurve::P256 => {
+            Some(&[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07])
+        }
+        jwk::EllipticCurve::P384 => Some(&[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22]),
+        jwk::EllipticCurve::P521 => Some(&[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x23]),
+        jwk::EllipticCurve::Ed25519 => None,
+    }
+}
+
+pub(crate) fn decode_b64(value: &str) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(value).ok()
+}
+
+pub(crate) fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn der_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![len as u8];
+    }
+
+    let bytes: Vec<u8> = len
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|b| *b == 0)
+        .collect();
+    [vec![0x80 | bytes.len() as u8], bytes].concat()
+}
+
+fn der_element(tag: u8, content: &[u8]) -> Vec<u8> {
+    [vec![tag], der_length(content.len()), content.to_vec()].concat()
+}
+
+fn der_integer(value: &[u8]) -> Vec<u8> {
+    let value = strip_leading_zeros(value);
+
+    // a set high bit would make the integer negative
+    match value.first() {
+        Some(b) if b & 0x80 == 0 => der_element(0x02, value),
+        _ => der_element(0x02, &[&[0x00], value].concat()),
+    }
+}
+
+fn spki_der(algorithm_identifier: &[u8], public_key: &[u8]) -> Vec<u8> {
+    let bit_string = der_element(0x03, &[&[0x00], public_key].concat());
+    der_element(0x30, &[algorithm_identifier, &bit_string].concat())
+}
+
+/// Encodes an RSA public key as a DER SubjectPublicKeyInfo structure.
+pub(crate) fn rsa_spki_der(modulus: &[u8], exponent: &[u8]) -> Vec<u8> {
+    let rsa_public_key = der_element(
+        0x30,
+        &[der_integer(modulus), der_integer(exponent)].concat(),
+    );
+    spki_der(&RSA_ALGORITHM_IDENTIFIER, &rsa_public_key)
+}
+
+/// Encodes the public key of a JWK as a DER SubjectPublicKeyInfo structure.
+/// Returns `None` if the key parameters could not be decoded.
+pub(crate) fn jwk_spki_der(jwk: &jwk::Jwk) -> Option<Vec<u8>> {
+    match &jwk.algorithm {
+        jwk::AlgorithmParameters::RSA(params) => Some(rsa_spki_der(
+            &decode_b64(&params.n)?,
+            &decode_b64(&params.e)?,
+        )),
+        jwk::AlgorithmParameters::EllipticCurve(params) => {
+            let algorithm_identifier = der_element(
+                0x30,
+                &[&EC_PUBLIC_KEY_OID[..], get_curve_oid(&params.curve)?].concat(),
+            );
+            // uncompressed SEC1 point: 0x04 || x || y
+            let point = [
+                &[0x04][..],
+                &decode_b64(&params.x)?,
+                &decode_b64(&params.y)?,
+            ]
+            .concat();
+            Some(spki_der(&algorithm_identifier, &point))
+        }
+        jwk::AlgorithmParameters::OctetKeyPair(params)
+            if params.curve == jwk::EllipticCurve::Ed25519 =>
+        {
+            Some(spki_der(
+                &ED25519_ALGORITHM_IDENTIFIER,
+                &decode_b64(&params.x)?,
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Wraps DER encoded data in a PEM block with the given label.
+pub(crate) fn to_pem(label: &str, der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let lines: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(PEM_LINE_LENGTH)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .collect();
+
+    format!(
+        "-----BEGIN {label}-----\n{}\n-----END {label}-----\n",
+        lines.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_der_length() {
+        assert_eq!(der_length(0x7f), vec![0x7f]);
+        assert_eq!(der_length(0x80), vec![0x81, 0x80]);
+        assert_eq!(der_length(0x010e), vec![0x82, 0x01, 0x0e]);
+    }
+
+    #[test]
+    fn test_der_integer() {
+        assert_eq!(
+            der_integer(&[0x01, 0x00, 0x01]),
+            vec![0x02, 0x03, 0x01, 0x00, 0x01]
+        );
+        assert_eq!(der_integer(&[0x00, 0x80]), vec![0x02, 0x02, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn test_to_pem() {
+        let pem = to_pem("PUBLIC KEY", &[0u8; 60]);
+        let lines: Vec<&str> = pem.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "-----BEGIN PUBLIC KEY-----");
+        assert_eq!(lines[1].len(), 64);
+        assert_eq!(lines[3], "-----END PUBLIC KEY-----");
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    errors::{ValidationError, ValidationResult},
+    inspect, DecodedToken, StdResult, Validator,
+};
+
+use std::env;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, TokenData};
+use serde_json::{json, Map, Value};
+
+/// The environment variable that must be set to `1` or `true` to activate a DevBypassValidator.
+pub const DEV_BYPASS_ENV_VAR: &str = "CFZT_DEV_BYPASS";
+
+const DEV_KEY_ID: &str = "cfzt-dev-bypass";
+const TOKEN_LIFETIME: u64 = 3600;
+
+fn is_enabled(value: Option<&str>) -> bool {
+    matches!(
+        value.map(|value| value.trim().to_lowercase()).as_deref(),
+        Some("1" | "true")
+    )
+}
+
+fn generate_secret() -> ValidationResult<String> {
+    let mut secret = [0u8; 32];
+    getrandom::getrandom(&mut secret)
+        .map_err(|err| ValidationError::dev_bypass_secret_unavailable(&err.to_string()))?;
+    Ok(URL_SAFE_NO_PAD.encode(secret))
+}
+
+/// A Validator for local development, where requests do not pass through Cloudflare Access.
+///
+/// Requests without a token are accepted as the configured identity, and tokens minted
+/// with `mint_token` are accepted as the identity they carry. Real CF tokens are rejected.
+/// Only compiled with the `dev-bypass` feature, and only activated when the
+/// `CFZT_DEV_BYPASS` environment variable is also set. Activation and every accepted
+/// request are logged as tracing warnings.
+pub struct DevBypassValidator {
+    team_name: String,
+    claims: Map<String, Value>,
+    secret: String,
+}
+
+impl DevBypassValidator {
+    /// Activates a DevBypassValidator for a team, identifying requests as `dev@localhost`.
+    /// Fails unless the `CFZT_DEV_BYPASS` environment variable is set to `1` or `true`.
+    pub fn activate(team_name: &str) -> ValidationResult<Self> {
+        Self::activate_with(team_name, env::var(DEV_BYPASS_ENV_VAR).ok().as_deref())
+    }
+
+    fn activate_with(team_name: &str, env_value: Option<&str>) -> ValidationResult<Self> {
+        if !is_enabled(env_value) {
+            return Err(ValidationError::dev_bypass_disabled(DEV_BYPASS_ENV_VAR));
+        }
+
+        tracing::warn!(
+            team = team_name,
+            "cfzt dev bypass is ACTIVE, requests are accepted without Cloudflare Access \
+             validation. Never enable {DEV_BYPASS_ENV_VAR} outside local development"
+        );
+
+        let claims = json!({
+            "email": "dev@localhost",
+            "sub": "00000000-0000-0000-0000-000000000000",
+            "country": "XX",
+            "custom": {},
+            "identity_nonce": "dev-bypass",
+            "type": "app",
+        });
+
+        Ok(DevBypassValidator {
+            team_name: team_name.to_string(),
+            claims: claims.as_object().unwrap().clone(),
+            secret: generate_secret()?,
+        })
+    }
+
+    /// Sets the email of the configured identity.
+    pub fn with_email(self, email: &str) -> Self {
+        self.with_claim("email", json!(email))
+    }
+
+    /// Sets the sub of the configured identity.
+    pub fn with_sub(self, sub: &str) -> Self {
+        self.with_claim("sub", json!(sub))
+    }
+
+    /// Sets the country of the configured identity.
+    pub fn with_country(self, country: &str) -> Self {
+        self.with_claim("country", json!(country))
+    }
+
+    /// Sets a claim of the configured identity, e.g. `custom` for IdP attributes.
+    pub fn with_claim(mut self, key: &str, value: Value) -> Self {
+        self.claims.insert(key.to_string(), value);
+        self
+    }
+
+    /// Sets the HMAC secret used to mint and accept local tokens, e.g. to share tokens
+    /// between several local services. A random secret is generated on activation otherwise.
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        self.secret = secret.to_string();
+        self
+    }
+
+    fn get_identity_claims(&self, audiences: Vec<String>) -> Map<String, Value> {
+        let now = SystemClock.timestamp();
+
+        let mut claims = self.claims.clone();
+        claims.insert("aud".to_string(), json!(audiences));
+        claims.insert("iat".to_string(), json!(now));
+        claims.insert("nbf".to_string(), json!(now));
+        claims.insert("exp".to_string(), json!(now + TOKEN_LIFETIME));
+        claims.insert(
+            "iss".to_string(),
+            json!(inspect::get_team_issuer(&self.team_name)),
+        );
+        claims
+    }
+
+    fn get_header() -> Header {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(DEV_KEY_ID.to_string());
+        header
+    }
+
+    /// Mints a local token for an audience, carrying the configured identity with the given email.
+    pub fn mint_token(&self, audience: &str, email: &str) -> StdResult<String> {
+        let mut claims = self.get_identity_claims(vec![audience.to_string()]);
+        claims.insert("email".to_string(), json!(email));
+
+        Ok(jsonwebtoken::encode(
+            &Self::get_header(),
+            &claims,
+            &EncodingKey::from_secret(self.secret.as_bytes()),
+        )?)
+    }
+}
+
+impl Validator for DevBypassValidator {
+    /// Accepts an empty token as the configured identity, or a token minted by `mint_token`
+    /// if it satisfies the constraints. The algorithms of the constraints are ignored.
+    fn validate_token(
+        &self,
+        token: &str,
+        team_name: &str,
+        constraints: &mut jsonwebtoken::Validation,
+    ) -> ValidationResult<DecodedToken> {
+        if team_name != self.team_name {
+            return Err(ValidationError::team_name_mismatch(
+                team_name,
+                self.team_name.as_str(),
+            ));
+        }
+
+        let token_data = match token.trim() {
+            "" => {
+                let audiences = constraints
+                    .aud
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<String>>();
+
+                TokenData {
+                    header: Self::get_header(),
+                    claims: Value::Object(self.get_identity_claims(audiences)),
+                }
+            }
+            token => {
+                let mut constraints = constraints.clone();
+                constraints.algorithms = vec![Algorithm::HS256];
+                let key = DecodingKey::from_secret(self.secret.as_bytes());
+                crate::decode_token(token, &key, &constraints, SystemClock.timestamp())?
+            }
+        };
+
+        tracing::warn!(
+            team = team_name,
+            email = token_data.claims["email"].as_str().unwrap_or_default(),
+            "cfzt dev bypass accepted request"
+        );
+        Ok(token_data)
+    }
+
+    /// Local identities have no keys to sync.
+    fn sync(&self) -> StdResult<bool> {
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_token::ApplicationToken;
+
+    const TEAM_NAME: &str = "molten";
+    const AUDIENCE: &str = "dev-audience";
+
+    fn get_constraints() -> jsonwebtoken::Validation {
+        let mut constraints = jsonwebtoken::Validation::new(Algorithm::RS256);
+        constraints.set_audience(&[AUDIENCE]);
+        constraints
+    }
+
+    #[test]
+    fn test_activation() {
+        assert!(DevBypassValidator::activate_with(TEAM_NAME, None).is_err());
+        assert!(DevBypassValidator::activate_with(TEAM_NAME, Some("0")).is_err());
+        assert!(DevBypassValidator::activate_with(TEAM_NAME, Some("yes")).is_err());
+        assert!(DevBypassValidator::activate_with(TEAM_NAME, Some("1")).is_ok());
+        assert!(DevBypassValidator::activate_with(TEAM_NAME, Some("TRUE")).is_ok());
+    }
+
+    #[test]
+    fn test_bypass_identity() {
+        let validator = DevBypassValidator::activate_with(TEAM_NAME, Some("1"))
+            .unwrap()
+            .with_email("alice@example.com")
+            .with_country("AU")
+            .with_claim("custom", json!({"groups": ["admins"]}));
+
+        let token_data = validator
+            .validate_token("", TEAM_NAME, &mut get_constraints())
+            .unwrap();
+        let app_token = ApplicationToken::from_token_data(token_data).unwrap();
+        assert_eq!(app_token.email, "alice@example.com");
+        assert_eq!(app_token.country, "AU");
+        assert_eq!(app_token.custom["groups"], json!(["admins"]));
+
+        assert!(validator
+            .validate_token("", "other", &mut get_constraints())
+            .is_err());
+    }
+
+    #[test]
+    fn test_minted_tokens() {
+        let validator = DevBypassValidator::activate_with(TEAM_NAME, Some("1")).unwrap();
+
+        let token = validator.mint_token(AUDIENCE, "bob@example.com").unwrap();
+        let token_data = validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .unwrap();
+        assert_eq!(token_data.claims["email"], "bob@example.com");
+
+        let token = validator
+            .mint_token("other-audience", "bob@example.com")
+            .unwrap();
+        assert!(validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .is_err());
+
+        // every activation generates its own secret, unless one is shared
+        let other = DevBypassValidator::activate_with(TEAM_NAME, Some("1")).unwrap();
+        let token = other.mint_token(AUDIENCE, "mallory@example.com").unwrap();
+        assert!(validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .is_err());
+
+        let validator = validator.with_secret("shared-secret");
+        let other = other.with_secret("shared-secret");
+        let token = other.mint_token(AUDIENCE, "carol@example.com").unwrap();
+        assert!(validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .is_ok());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::api::TeamKeys;
+
+use std::{collections::HashSet, fmt};
+
+/// The differences between two key sets of a team, e.g. two snapshots
+/// or a snapshot and the keys currently published by the CF API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySetDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Key IDs present in both key sets whose key material differs.
+    pub changed: Vec<String>,
+    pub previous_latest_key_id: String,
+    pub latest_key_id: String,
+}
+
+fn sorted_difference(a: &HashSet<&String>, b: &HashSet<&String>) -> Vec<String> {
+    let mut key_ids: Vec<String> = a.difference(b).map(|key_id| key_id.to_string()).collect();
+    key_ids.sort();
+    key_ids
+}
+
+impl KeySetDiff {
+    /// Compares a previous key set to a current one.
+    pub fn between(previous: &TeamKeys, current: &TeamKeys) -> Self {
+        let previous_key_ids: HashSet<&String> = previous.keys.keys().collect();
+        let current_key_ids: HashSet<&String> = current.keys.keys().collect();
+
+        let mut changed: Vec<String> = previous_key_ids
+            .intersection(&current_key_ids)
+            .filter(|key_id| previous.keys[**key_id].get_jwk() != current.keys[**key_id].get_jwk())
+            .map(|key_id| key_id.to_string())
+            .collect();
+        changed.sort();
+
+        KeySetDiff {
+            added: sorted_difference(&current_key_ids, &previous_key_ids),
+            removed: sorted_difference(&previous_key_ids, &current_key_ids),
+            changed,
+            previous_latest_key_id: previous.latest_key_id.clone(),
+            latest_key_id: current.latest_key_id.clone(),
+        }
+    }
+
+    /// Returns whether the latest key changed.
+    pub fn is_rotation(&self) -> bool {
+        self.previous_latest_key_id != self.latest_key_id
+    }
+
+    /// Returns whether the key sets are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && !self.is_rotation()
+    }
+}
+
+impl fmt::Display for KeySetDiff {
+    /// Writes one line per change, `+` for added keys, `-` for removed keys,
+    /// `~` for keys republished with different key material and `*` for a change of the latest key.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut lines: Vec<String> = Vec::new();
+
+        lines.extend(self.added.iter().map(|key_id| format!("+ {key_id}")));
+        lines.extend(self.removed.iter().map(|key_id| format!("- {key_id}")));
+        lines.extend(self.changed.iter().map(|key_id| format!("~ {key_id}")));
+
+        if self.is_rotation() {
+            lines.push(format!(
+                "* latest {} -> {}",
+                self.previous_latest_key_id, self.latest_key_id
+            ));
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockIssuer;
+
+    const TEAM_NAME: &str = "molten";
+
+    #[test]
+    fn test_key_set_diff() {
+        let mut issuer = MockIssuer::new(TEAM_NAME);
+        let key_id_1 = issuer.get_latest_key_id();
+        let keys_1 = issuer.to_team_keys();
+        let key_id_2 = issuer.rotate();
+        issuer.retire(&key_id_1);
+        let keys_2 = issuer.to_team_keys();
+
+        assert!(KeySetDiff::between(&keys_1, &keys_1).is_empty());
+
+        let diff = KeySetDiff::between(&keys_1, &keys_2);
+        assert!(!diff.is_empty());
+        assert!(diff.is_rotation());
+        assert_eq!(diff.added, vec![key_id_2.clone()]);
+        assert_eq!(diff.removed, vec![key_id_1.clone()]);
+        assert_eq!(
+            diff.to_string(),
+            format!("+ {key_id_2}\n- {key_id_1}\n* latest {key_id_1} -> {key_id_2}")
+        );
+    }
+
+    #[test]
+    fn test_key_set_diff_changed_key() {
+        let issuer = MockIssuer::new(TEAM_NAME);
+        let key_id = issuer.get_latest_key_id();
+        let keys_1 = issuer.to_team_keys();
+
+        // the same kid republished with the key material of another key
+        let mut payload = issuer.to_certs_json();
+        payload["keys"][0]["n"] =
+            MockIssuer::new(TEAM_NAME).to_certs_json()["keys"][0]["n"].clone();
+        let republished = TeamKeys::from_json(TEAM_NAME, payload).unwrap();
+
+        let diff = KeySetDiff::between(&keys_1, &republished);
+        assert!(!diff.is_empty());
+        assert!(!diff.is_rotation());
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.changed, vec![key_id.clone()]);
+        assert_eq!(diff.to_string(), format!("~ {key_id}"));
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    api::{ParseMode, TeamKeys},
+    errors::KeySetError,
+    key_policy::KeyPolicy,
+    StdResult,
+};
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use jsonwebtoken::DecodingKey;
+
+// strict and reserved keywords, which cannot name the generated constructors
+const KEYWORDS: [&str; 52] = [
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if",
+    "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn is_identifier(name: &str) -> bool {
+    if KEYWORDS.contains(&name) {
+        return false;
+    }
+
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    name != "_" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+struct EmbeddedTeam {
+    fn_name: String,
+    team_name: String,
+    certs_path: PathBuf,
+}
+
+/// Generates `TeamKeys` constructors for certs JSON files, for use in build scripts.
+///
+/// Key sets are parsed and every key is checked against a KeyPolicy when the build
+/// script runs, so that invalid key material fails the build. The generated constructors
+/// embed the parsed key set and can be included with
+/// `include!(concat!(env!("OUT_DIR"), "/<file>"))`.
+///
+/// ```no_run
+/// // build.rs
+/// use rust_cfzt_validator::embed::KeySetEmbedder;
+///
+/// let out_dir = std::env::var("OUT_DIR").unwrap();
+/// KeySetEmbedder::new()
+///     .with_team("molten_keys", "molten", "keys/molten.json")
+///     .write_to(format!("{out_dir}/team_keys.rs"))
+///     .unwrap();
+/// ```
+pub struct KeySetEmbedder {
+    parse_mode: ParseMode,
+    key_policy: KeyPolicy,
+    teams: Vec<EmbeddedTeam>,
+}
+
+impl Default for KeySetEmbedder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeySetEmbedder {
+    /// Constructs a KeySetEmbedder with no teams.
+    pub fn new() -> Self {
+        KeySetEmbedder {
+            parse_mode: ParseMode::Strict,
+            key_policy: KeyPolicy::default(),
+            teams: Vec::new(),
+        }
+    }
+
+    /// Sets the ParseMode used to parse the key sets. Keys skipped in
+    /// `ParseMode::Lenient` are left out of the generated constructors.
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// Sets the KeyPolicy every embedded key must satisfy, `KeyPolicy::default()` if unset.
+    pub fn with_key_policy(mut self, policy: KeyPolicy) -> Self {
+        self.key_policy = policy;
+        self
+    }
+
+    /// Generates a function named `fn_name` returning the keys of a team read from `certs_path`.
+    pub fn with_team<P: AsRef<Path>>(
+        mut self,
+        fn_name: &str,
+        team_name: &str,
+        certs_path: P,
+    ) -> Self {
+        self.teams.push(EmbeddedTeam {
+            fn_name: fn_name.to_string(),
+            team_name: team_name.to_string(),
+            certs_path: certs_path.as_ref().to_path_buf(),
+        });
+        self
+    }
+
+    fn load_team_keys(&self, team: &EmbeddedTeam) -> StdResult<TeamKeys> {
+        let json_str = fs::read_to_string(&team.certs_path)
+            .map_err(|err| format!("failed to read {}: {err}", team.certs_path.display()))?;
+        let json_val = serde_json::from_str(&json_str)
+            .map_err(|err| format!("failed to parse {}: {err}", team.certs_path.display()))?;
+
+        let team_keys = TeamKeys::from_json_with_mode(&team.team_name, json_val, self.parse_mode)
+            .map_err(|err| {
+            format!("invalid key set in {}: {err}", team.certs_path.display())
+        })?;
+
+        for access_key in team_keys.keys.values() {
+            self.key_policy.check_key(access_key.as_ref())?;
+            DecodingKey::from_jwk(&access_key.get_jwk()).map_err(|err| {
+                format!(
+                    "invalid key '{}' in {}: {err}",
+                    access_key.get_key_id(),
+                    team.certs_path.display()
+                )
+            })?;
+        }
+
+        Ok(team_keys)
+    }
+
+    /// Validates every key set and returns the generated Rust source.
+    pub fn generate(&self) -> StdResult<String> {
+        let mut source = String::from("// @generated by rust_cfzt_validator::embed, do not edit\n");
+
+        for team in &self.teams {
+            if !is_identifier(&team.fn_name) {
+                return Err(Box::new(KeySetError::invalid_constructor_name(
+                    &team.fn_name,
+                )));
+            }
+
+            let payload = self.load_team_keys(team)?.to_certs_json().to_string();
+
+            write!(
+                source,
+                "
+/// Returns the keys of team `{team_name}`, embedded from `{path}`.
+pub fn {fn_name}() -> ::rust_cfzt_validator::api::TeamKeys {{
+    ::rust_cfzt_validator::api::TeamKeys::from_str({team_name:?}, {payload:?})
+        .expect(\"embedded key set was validated at build time\")
+}}
+",
+                team_name = team.team_name,
+                path = team.certs_path.display(),
+                fn_name = team.fn_name,
+            )?;
+        }
+
+        Ok(source)
+    }
+
+    /// Writes the generated Rust source to `out_path`, and instructs cargo
+    /// to rerun the build script when any of the certs files change.
+    pub fn write_to<P: AsRef<Path>>(&self, out_path: P) -> StdResult<()> {
+        let source = self.generate()?;
+        fs::write(out_path, source)?;
+
+        for team in &self.teams {
+            println!("cargo:rerun-if-changed={}", team.certs_path.display());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATIC_KEYS_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test_data/sample_signing_keys.json"
+    );
+    const DUMMY_KEYS_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test_data/dummy_signing_keys.json"
+    );
+
+    fn get_generated_payload(source: &str) -> String {
+        let start = source.find("from_str(\"molten\", ").unwrap() + "from_str(\"molten\", ".len();
+        let end = source[start..].find(")\n").unwrap() + start;
+        serde_json::from_str::<String>(&source[start..end]).unwrap()
+    }
+
+    #[test]
+    fn test_generate() {
+        let source = KeySetEmbedder::new()
+            .with_team("molten_keys", "molten", STATIC_KEYS_PATH)
+            .generate()
+            .unwrap();
+
+        assert!(source.contains("pub fn molten_keys() -> ::rust_cfzt_validator::api::TeamKeys {"));
+
+        let team_keys = TeamKeys::from_str("molten", &get_generated_payload(&source)).unwrap();
+        let expected =
+            TeamKeys::from_str("molten", &fs::read_to_string(STATIC_KEYS_PATH).unwrap()).unwrap();
+        assert_eq!(team_keys.to_jwks(), expected.to_jwks());
+    }
+
+    #[test]
+    fn test_generate_rejects_invalid_input() {
+        let result = KeySetEmbedder::new()
+            .with_team("dummy_keys", "dummy", DUMMY_KEYS_PATH)
+            .generate();
+        assert!(result.is_err());
+
+        for fn_name in ["molten-keys", "fn", "type", "Self"] {
+            let result = KeySetEmbedder::new()
+                .with_team(fn_name, "molten", STATIC_KEYS_PATH)
+                .generate();
+            assert!(result.is_err());
+        }
+
+        let result = KeySetEmbedder::new()
+            .with_team("molten_keys", "molten", "missing.json")
+            .generate();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,465 @@
+use crate::{testing::MockIssuer, TeamValidator, Validator};
+
+use std::{io::Read, time::Duration};
+
+use jsonwebtoken::{Algorithm, Validation};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const CERTS_PATH: &str = "/cdn-cgi/access/certs";
+const LOGIN_PATH: &str = "/cdn-cgi/access/login";
+const LOGOUT_PATH: &str = "/cdn-cgi/access/logout";
+const COOKIE_NAME: &str = "CF_Authorization";
+const ASSERTION_HEADER: &str = "Cf-Access-Jwt-Assertion";
+
+// headers describing a single hop, which are not forwarded in either direction
+const HOP_HEADERS: [&str; 7] = [
+    "connection",
+    "content-length",
+    "host",
+    "keep-alive",
+    "transfer-encoding",
+    "upgrade",
+    "cf-access-jwt-assertion",
+];
+
+type EmulatorResponse = Response<std::io::Cursor<Vec<u8>>>;
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn get_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn is_local_path(url: &str) -> bool {
+    // browsers treat "//host" and "/\\host" as protocol-relative URLs, and drop tabs and newlines
+    url.starts_with('/')
+        && !url.starts_with("//")
+        && !url.contains('\\')
+        && !url.chars().any(|c| c.is_control())
+}
+
+/// Only local paths are accepted as redirect targets, so the login page cannot be used as an open redirect.
+fn get_redirect_url(redirect_url: Option<String>) -> String {
+    match redirect_url {
+        Some(url) if is_local_path(&url) => url,
+        _ => "/".to_string(),
+    }
+}
+
+fn get_cookie_token(cookies: &str) -> Option<&str> {
+    cookies
+        .split(';')
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == COOKIE_NAME)
+        .map(|(_, value)| value)
+}
+
+fn get_header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn redirect(location: &str) -> EmulatorResponse {
+    Response::from_data(Vec::new())
+        .with_status_code(302)
+        .with_header(Header::from_bytes("Location", location).unwrap())
+}
+
+fn text(status: u16, content_type: &str, body: String) -> EmulatorResponse {
+    Response::from_data(body.into_bytes())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", content_type).unwrap())
+}
+
+/// Emulates the Cloudflare Access edge in front of a local application, for development
+/// without a Cloudflare account.
+///
+/// Unauthenticated requests are redirected to a login page offering a fixed set of identities.
+/// Logging in sets a `CF_Authorization` cookie, and authenticated requests are proxied upstream
+/// with a `Cf-Access-Jwt-Assertion` header. Tokens are signed with a MockIssuer whose keys are
+/// served at `/cdn-cgi/access/certs`, so applications can validate them with a TeamValidator.
+pub struct AccessEmulator {
+    issuer: MockIssuer,
+    validator: TeamValidator,
+    audience: String,
+    upstream: String,
+    identities: Vec<String>,
+    agent: ureq::Agent,
+}
+
+impl AccessEmulator {
+    /// Constructs an AccessEmulator for a team and application audience,
+    /// proxying to an upstream base URL, e.g. `http://127.0.0.1:3000`.
+    pub fn new(team_name: &str, audience: &str, upstream: &str) -> Self {
+        let issuer = MockIssuer::new(team_name);
+
+        AccessEmulator {
+            validator: TeamValidator::from_team_keys(issuer.to_team_keys()),
+            issuer,
+            audience: audience.to_string(),
+            upstream: upstream.trim_end_matches('/').to_string(),
+            identities: vec!["dev@localhost".to_string()],
+            agent: ureq::AgentBuilder::new()
+                .redirects(0)
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    /// Sets the emails offered on the login page.
+    pub fn with_identities(mut self, identities: &[&str]) -> Self {
+        self.identities = identities.iter().map(|email| email.to_string()).collect();
+        self
+    }
+
+    /// Returns the MockIssuer signing the emulator's tokens.
+    pub fn get_issuer(&self) -> &MockIssuer {
+        &self.issuer
+    }
+
+    /// Mints a token for one of the configured identities.
+    pub fn login(&self, email: &str) -> Option<String> {
+        if !self.identities.iter().any(|identity| identity == email) {
+            return None;
+        }
+
+        Some(
+            self.issuer
+                .app_token(&self.audience)
+                .with_claim("email", json!(email))
+                .sign(),
+        )
+    }
+
+    /// Returns the token of a request's `CF_Authorization` cookie if it is valid.
+    pub fn get_session(&self, cookies: Option<&str>) -> Option<String> {
+        let token = get_cookie_token(cookies?)?;
+
+        let mut constraints = Validation::new(Algorithm::RS256);
+        constraints.set_audience(&[&self.audience]);
+        self.validator
+            .validate_token(token, &self.issuer.get_team_name(), &mut constraints)
+            .ok()
+            .map(|_| token.to_string())
+    }
+
+    fn get_login_page(&self, redirect_url: &str) -> EmulatorResponse {
+        let buttons: String = self
+            .identities
+            .iter()
+            .map(|email| {
+                format!(
+                    "<button type=\"submit\" name=\"email\" value=\"{0}\">{0}</button>\n",
+                    escape_html(email)
+                )
+            })
+            .collect();
+
+        let body = format!(
+            "<!DOCTYPE html>\n<html>\n<head><title>Access emulator</title></head>\n<body>\n\
+             <h1>Sign in to {team}</h1>\n<p>Local Cloudflare Access emulator, choose an identity.</p>\n\
+             <form method=\"post\" action=\"{LOGIN_PATH}\">\n\
+             <input type=\"hidden\" name=\"redirect_url\" value=\"{redirect_url}\">\n\
+             {buttons}</form>\n</body>\n</html>\n",
+            team = escape_html(&self.issuer.get_team_name()),
+            redirect_url = escape_html(redirect_url),
+        );
+
+        text(200, "text/html; charset=utf-8", body)
+    }
+
+    fn post_login(&self, form: &str) -> EmulatorResponse {
+        let redirect_url = get_redirect_url(get_param(form, "redirect_url"));
+        let email = get_param(form, "email").unwrap_or_default();
+
+        match self.login(&email) {
+            Some(token) => {
+                eprintln!("[cfzt access emulator] signed in as '{email}'");
+                let cookie = format!("{COOKIE_NAME}={token}; Path=/; HttpOnly; SameSite=Lax");
+                redirect(&redirect_url)
+                    .with_header(Header::from_bytes("Set-Cookie", cookie).unwrap())
+            }
+            None => text(403, "text/plain", format!("unknown identity '{email}'\n")),
+        }
+    }
+
+    fn logout(&self) -> EmulatorResponse {
+        let cookie = format!("{COOKIE_NAME}=; Path=/; Max-Age=0");
+        redirect(LOGIN_PATH).with_header(Header::from_bytes("Set-Cookie", cookie).unwrap())
+    }
+
+    fn proxy(&self, request: &mut Request, token: &str) -> EmulatorResponse {
+        let url = format!("{}{}", self.upstream, request.url());
+        let mut upstream_request = self.agent.request(request.method().as_str(), &url);
+
+        for header in request.headers() {
+            let name = header.field.as_str().as_str();
+            if !HOP_HEADERS.contains(&name.to_lowercase().as_str()) {
+                upstream_request = upstream_request.set(name, header.value.as_str());
+            }
+        }
+        upstream_request = upstream_request.set(ASSERTION_HEADER, token);
+
+        let mut body = Vec::new();
+        if request.as_reader().read_to_end(&mut body).is_err() {
+            return text(
+                400,
+                "text/plain",
+                "failed to read request body\n".to_string(),
+            );
+        }
+
+        let upstream_response = match upstream_request.send_bytes(&body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(err) => return text(502, "text/plain", format!("upstream unavailable: {err}\n")),
+        };
+
+        let status = upstream_response.status();
+        let mut headers = Vec::new();
+        for name in upstream_response.headers_names() {
+            if HOP_HEADERS.contains(&name.to_lowercase().as_str()) {
+                continue;
+            }
+            for value in upstream_response.all(&name) {
+                if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                    headers.push(header);
+                }
+            }
+        }
+
+        let mut body = Vec::new();
+        if upstream_response
+            .into_reader()
+            .read_to_end(&mut body)
+            .is_err()
+        {
+            return text(
+                502,
+                "text/plain",
+                "failed to read upstream response\n".to_string(),
+            );
+        }
+
+        headers.into_iter().fold(
+            Response::from_data(body).with_status_code(status),
+            |response, header| response.with_header(header),
+        )
+    }
+
+    /// Resolves a request, serving the emulator's own endpoints or proxying it upstream.
+    pub fn handle(&self, request: &mut Request) -> EmulatorResponse {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+        match (request.method(), path) {
+            (Method::Get, CERTS_PATH) => text(
+                200,
+                "application/json",
+                self.issuer.to_certs_json().to_string(),
+            ),
+            (Method::Get, LOGIN_PATH) => {
+                self.get_login_page(&get_redirect_url(get_param(query, "redirect_url")))
+            }
+            (Method::Post, LOGIN_PATH) => {
+                let mut form = String::new();
+                match request.as_reader().read_to_string(&mut form) {
+                    Ok(_) => self.post_login(&form),
+                    Err(_) => text(400, "text/plain", "failed to read form\n".to_string()),
+                }
+            }
+            (_, LOGOUT_PATH) => self.logout(),
+            _ => match self.get_session(get_header(request, "Cookie")) {
+                Some(token) => self.proxy(request, &token),
+                None => redirect(&format!(
+                    "{LOGIN_PATH}?redirect_url={}",
+                    percent_encode(&url)
+                )),
+            },
+        }
+    }
+
+    /// Serves requests from a tiny_http Server until it is unblocked.
+    pub fn serve(&self, server: &Server) {
+        for mut request in server.incoming_requests() {
+            let response = self.handle(&mut request);
+            let _ = request.respond(response);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::TeamKeys;
+    use std::{sync::Arc, thread};
+
+    const TEAM_NAME: &str = "molten";
+    const AUDIENCE: &str = "mock-audience";
+
+    fn start_upstream() -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let assertion = get_header(&request, ASSERTION_HEADER).unwrap_or_default();
+                let body = json!({"url": request.url(), "assertion": assertion}).to_string();
+                let _ = request.respond(Response::from_string(body).with_status_code(201));
+            }
+        });
+
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn test_form_encoding() {
+        let form = "email=alice%40example.com&redirect_url=%2Fapp%3Fx%3D1+2";
+        assert_eq!(get_param(form, "email").unwrap(), "alice@example.com");
+        assert_eq!(get_param(form, "redirect_url").unwrap(), "/app?x=1 2");
+        assert_eq!(
+            percent_decode(&percent_encode("/app?x=1&y=ü")),
+            "/app?x=1&y=ü"
+        );
+
+        assert_eq!(get_redirect_url(Some("/app".to_string())), "/app");
+        assert_eq!(get_redirect_url(Some("//evil.example".to_string())), "/");
+        assert_eq!(get_redirect_url(Some("/\\evil.example".to_string())), "/");
+        assert_eq!(get_redirect_url(Some("/app\\..".to_string())), "/");
+        assert_eq!(get_redirect_url(Some("/\t/evil.example".to_string())), "/");
+        assert_eq!(
+            get_redirect_url(Some("https://evil.example".to_string())),
+            "/"
+        );
+    }
+
+    #[test]
+    fn test_login_flow() {
+        let emulator = Arc::new(
+            AccessEmulator::new(TEAM_NAME, AUDIENCE, &start_upstream())
+                .with_identities(&["alice@example.com", "bob@example.com"]),
+        );
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let serving = emulator.clone();
+        thread::spawn(move || serving.serve(&server));
+
+        let agent = ureq::AgentBuilder::new().redirects(0).build();
+
+        // unauthenticated requests are sent to the login page, even with a spoofed assertion
+        let response = agent
+            .get(&format!("{base}/app?x=1"))
+            .set(ASSERTION_HEADER, "spoofed")
+            .call()
+            .unwrap();
+        assert_eq!(response.status(), 302);
+        let login_url = response.header("Location").unwrap().to_string();
+        assert_eq!(
+            login_url,
+            "/cdn-cgi/access/login?redirect_url=%2Fapp%3Fx%3D1"
+        );
+
+        let page = agent
+            .get(&format!("{base}{login_url}"))
+            .call()
+            .unwrap()
+            .into_string()
+            .unwrap();
+        assert!(page.contains("bob@example.com"));
+
+        assert!(agent
+            .post(&format!("{base}{LOGIN_PATH}"))
+            .send_form(&[("email", "mallory@example.com")])
+            .is_err());
+
+        let response = agent
+            .post(&format!("{base}{LOGIN_PATH}"))
+            .send_form(&[("email", "bob@example.com"), ("redirect_url", "/app?x=1")])
+            .unwrap();
+        assert_eq!(response.status(), 302);
+        assert_eq!(response.header("Location"), Some("/app?x=1"));
+        let cookie = response.header("Set-Cookie").unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_string();
+
+        // authenticated requests are proxied with an assertion that validates against the served keys
+        let response = agent
+            .get(&format!("{base}/app?x=1"))
+            .set("Cookie", &cookie)
+            .call()
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        let body: serde_json::Value = response.into_json().unwrap();
+        assert_eq!(body["url"], "/app?x=1");
+
+        let team_keys = TeamKeys::from_url(TEAM_NAME, &format!("{base}{CERTS_PATH}")).unwrap();
+        let validator = TeamValidator::from_team_keys(team_keys);
+        let mut constraints = Validation::new(Algorithm::RS256);
+        constraints.set_audience(&[AUDIENCE]);
+        let token_data = validator
+            .validate_token(
+                body["assertion"].as_str().unwrap(),
+                TEAM_NAME,
+                &mut constraints,
+            )
+            .unwrap();
+        assert_eq!(token_data.claims["email"], "bob@example.com");
+
+        let response = agent.get(&format!("{base}{LOGOUT_PATH}")).call().unwrap();
+        assert!(response.header("Set-Cookie").unwrap().contains("Max-Age=0"));
+    }
+}
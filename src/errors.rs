@@ -2,6 +2,7 @@ use std::{error::Error, fmt};
 
 pub type UnpackResult<T> = Result<T, UnpackError>;
 pub type ValidationResult<T> = Result<T, ValidationError>;
+pub type KeySetResult<T> = Result<T, KeySetError>;
 
 #[derive(Debug)]
 pub struct UnpackError {
@@ -27,6 +28,24 @@ impl UnpackError {
         }
     }
 
+    pub fn unsupported_value(key: &str, value: &str) -> Self {
+        UnpackError {
+            message: format!("unsupported value '{value}' for key '{key}'"),
+        }
+    }
+
+    pub fn invalid_key(key_id: &str, reason: &str) -> Self {
+        UnpackError {
+            message: format!("key '{key_id}' is not usable: {reason}"),
+        }
+    }
+
+    pub fn unexpected_value(key: &str, value: &str, expect: &str) -> Self {
+        UnpackError {
+            message: format!("unexpected value '{value}' for key '{key}', expected {expect}"),
+        }
+    }
+
     pub fn number_parse_failure(expect: &str) -> Self {
         UnpackError {
             message: format!("failed parsing json number as {expect}"),
@@ -48,12 +67,20 @@ impl fmt::Display for UnpackError {
 
 #[derive(Debug)]
 pub struct ValidationError {
+    kind: &'static str,
     message: String,
 }
 
 impl ValidationError {
+    /// Returns the kind of the error, the name of the constructor that created it,
+    /// e.g. `invalid_jwt`, for grouping errors without parsing messages.
+    pub fn get_kind(&self) -> &'static str {
+        self.kind
+    }
+
     pub fn team_name_mismatch(expect: &str, actual: &str) -> Self {
         ValidationError {
+            kind: "team_name_mismatch",
             message: format!(
                 "provided team name '{actual}' does not match validator team name '{expect}'"
             ),
@@ -62,33 +89,159 @@ impl ValidationError {
 
     pub fn unknown_team_name(expect: &str) -> Self {
         ValidationError {
+            kind: "unknown_team_name",
             message: format!("team name '{expect}' not found"),
         }
     }
 
     pub fn header_missing_kid() -> Self {
         ValidationError {
+            kind: "header_missing_kid",
             message: "no kid in jwt header".to_string(),
         }
     }
 
     pub fn no_kid_in_cache(expect: &str) -> Self {
         ValidationError {
+            kind: "no_kid_in_cache",
             message: format!("kid '{expect}' not found in cache"),
         }
     }
 
+    pub fn undecodable_key(key_id: &str) -> Self {
+        ValidationError {
+            kind: "undecodable_key",
+            message: format!("kid '{key_id}' cannot be used to verify tokens"),
+        }
+    }
+
     pub fn header_decode_failure() -> Self {
         ValidationError {
+            kind: "header_decode_failure",
             message: "failed to decode jwt header".to_string(),
         }
     }
 
     pub fn invalid_jwt() -> Self {
         ValidationError {
+            kind: "invalid_jwt",
             message: "jwt is not valid".to_string(),
         }
     }
+
+    pub fn token_expired(exp: u64, now: u64) -> Self {
+        ValidationError {
+            kind: "token_expired",
+            message: format!("jwt expired at {exp}, current time is {now}"),
+        }
+    }
+
+    pub fn token_not_yet_valid(nbf: u64, now: u64) -> Self {
+        ValidationError {
+            kind: "token_not_yet_valid",
+            message: format!("jwt is not valid before {nbf}, current time is {now}"),
+        }
+    }
+
+    pub fn stale_keys(age: u64, max_age: u64) -> Self {
+        ValidationError {
+            kind: "stale_keys",
+            message: format!(
+                "keys were last refreshed {age}s ago, exceeding the maximum of {max_age}s"
+            ),
+        }
+    }
+
+    pub fn dev_bypass_disabled(env_var: &str) -> Self {
+        ValidationError {
+            kind: "dev_bypass_disabled",
+            message: format!("dev bypass requires the {env_var} environment variable to be set"),
+        }
+    }
+
+    pub fn dev_bypass_secret_unavailable(reason: &str) -> Self {
+        ValidationError {
+            kind: "dev_bypass_secret_unavailable",
+            message: format!("failed to generate a dev bypass secret: {reason}"),
+        }
+    }
+
+    pub fn country_not_permitted(country: &str, audience: &str) -> Self {
+        ValidationError {
+            kind: "country_not_permitted",
+            message: format!("country '{country}' is not permitted for audience '{audience}'"),
+        }
+    }
+
+    pub fn missing_country(audience: &str) -> Self {
+        ValidationError {
+            kind: "missing_country",
+            message: format!("no country claim in jwt for geo-restricted audience '{audience}'"),
+        }
+    }
+
+    pub fn no_route(host: &str, path: &str) -> Self {
+        ValidationError {
+            kind: "no_route",
+            message: format!("no route matches host '{host}' and path '{path}'"),
+        }
+    }
+
+    pub fn unknown_profile(expect: &str) -> Self {
+        ValidationError {
+            kind: "unknown_profile",
+            message: format!("constraints profile '{expect}' not found"),
+        }
+    }
+
+    pub fn nonce_mismatch() -> Self {
+        ValidationError {
+            kind: "nonce_mismatch",
+            message: "jwt nonce does not match expected nonce".to_string(),
+        }
+    }
+
+    pub fn azp_mismatch(expect: &str) -> Self {
+        ValidationError {
+            kind: "azp_mismatch",
+            message: format!("jwt azp does not match client id '{expect}'"),
+        }
+    }
+
+    pub fn constraint_conflict(claim: &str, expect: &str) -> Self {
+        ValidationError {
+            kind: "constraint_conflict",
+            message: format!("constraints on '{claim}' exclude the expected value '{expect}'"),
+        }
+    }
+
+    pub fn missing_token() -> Self {
+        ValidationError {
+            kind: "missing_token",
+            message: "no jwt found in request".to_string(),
+        }
+    }
+
+    pub fn missing_team_name() -> Self {
+        ValidationError {
+            kind: "missing_team_name",
+            message: "no team name configured for request".to_string(),
+        }
+    }
+
+    pub fn key_policy_violation(key_id: &str, reason: &str) -> Self {
+        ValidationError {
+            kind: "key_policy_violation",
+            message: format!("kid '{key_id}' violates key policy: {reason}"),
+        }
+    }
+
+    pub fn missing_audience() -> Self {
+        ValidationError {
+            kind: "missing_audience",
+            message: "no audience configured for request".to_string(),
+        }
+    }
 }
 
 impl Error for ValidationError {
@@ -102,3 +255,74 @@ impl fmt::Display for ValidationError {
         write!(f, "validation fail: {}", self.message)
     }
 }
+
+#[derive(Debug)]
+pub struct KeySetError {
+    message: String,
+}
+
+impl KeySetError {
+    pub fn certificate_parse_failure(key_id: &str) -> Self {
+        KeySetError {
+            message: format!("failed to parse certificate for kid '{key_id}'"),
+        }
+    }
+
+    pub fn certificate_key_mismatch(key_id: &str) -> Self {
+        KeySetError {
+            message: format!("certificate public key does not match jwk for kid '{key_id}'"),
+        }
+    }
+
+    pub fn key_id_mismatch(key_id: &str) -> Self {
+        KeySetError {
+            message: format!(
+                "kid '{key_id}' does not match the thumbprint of its key, the key set may be tampered or corrupted"
+            ),
+        }
+    }
+
+    pub fn latest_key_rejected(key_id: &str, reason: &str) -> Self {
+        KeySetError {
+            message: format!("latest kid '{key_id}' was rejected: {reason}"),
+        }
+    }
+
+    pub fn bundle_signature_invalid(reason: &str) -> Self {
+        KeySetError {
+            message: format!("key bundle signature could not be verified: {reason}"),
+        }
+    }
+
+    pub fn bundle_version_replayed(version: u64, last_version: u64) -> Self {
+        KeySetError {
+            message: format!(
+                "key bundle version {version} is not newer than the last imported version {last_version}"
+            ),
+        }
+    }
+
+    pub fn bundle_team_missing(team_name: &str) -> Self {
+        KeySetError {
+            message: format!("key bundle does not contain keys for team '{team_name}'"),
+        }
+    }
+
+    pub fn invalid_constructor_name(name: &str) -> Self {
+        KeySetError {
+            message: format!("'{name}' is not a valid Rust function name"),
+        }
+    }
+}
+
+impl Error for KeySetError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for KeySetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "key set fail: {}", self.message)
+    }
+}
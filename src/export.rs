@@ -0,0 +1,275 @@
+use crate::{api::TeamKeys, cache::Cache, der};
+
+use std::fmt;
+
+use jsonwebtoken::jwk;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// The role of an exported key within the trusted key set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStatus {
+    /// The latest key, used to sign new tokens.
+    Active,
+    /// Another trusted key, either published ahead of its promotion to the latest key,
+    /// or kept until tokens signed with it have expired. A key set does not tell these apart.
+    Inactive,
+}
+
+impl fmt::Display for KeyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyStatus::Active => write!(f, "active"),
+            KeyStatus::Inactive => write!(f, "inactive"),
+        }
+    }
+}
+
+/// A trusted key exported as a PEM encoded SubjectPublicKeyInfo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PemKey {
+    pub key_id: String,
+    pub status: KeyStatus,
+    pub pem: String,
+}
+
+impl fmt::Display for PemKey {
+    /// Writes the PEM block preceded by its kid and status as explanatory text,
+    /// so that several keys can be concatenated into a single bundle.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "kid: {}\nstatus: {}\n{}",
+            self.key_id, self.status, self.pem
+        )
+    }
+}
+
+fn get_status(latest_key_id: &str, jwk: &jwk::Jwk) -> KeyStatus {
+    match jwk.common.key_id.as_deref() == Some(latest_key_id) {
+        true => KeyStatus::Active,
+        false => KeyStatus::Inactive,
+    }
+}
+
+fn sort_jwks(latest_key_id: &str, mut jwks: Vec<jwk::Jwk>) -> Vec<jwk::Jwk> {
+    // the latest key first, as consumers commonly pick the first key
+    jwks.sort_by_key(|jwk| {
+        (
+            get_status(latest_key_id, jwk) != KeyStatus::Active,
+            jwk.common.key_id.clone(),
+        )
+    });
+    jwks
+}
+
+fn export_jwks(latest_key_id: &str, jwks: Vec<jwk::Jwk>) -> Value {
+    let keys: Vec<Value> = sort_jwks(latest_key_id, jwks)
+        .iter()
+        .map(|jwk| {
+            let mut val = serde_json::to_value(jwk).unwrap();
+            val["status"] = json!(get_status(latest_key_id, jwk));
+            val
+        })
+        .collect();
+
+    json!({
+        "keys": keys,
+        "latest_kid": latest_key_id,
+    })
+}
+
+fn export_pem(latest_key_id: &str, jwks: Vec<jwk::Jwk>) -> Vec<PemKey> {
+    sort_jwks(latest_key_id, jwks)
+        .iter()
+        .filter_map(|jwk| {
+            Some(PemKey {
+                key_id: jwk.common.key_id.clone()?,
+                status: get_status(latest_key_id, jwk),
+                pem: der::to_pem("PUBLIC KEY", &der::jwk_spki_der(jwk)?),
+            })
+        })
+        .collect()
+}
+
+fn get_public_cert(team_keys: &TeamKeys, key_id: &str) -> Value {
+    match team_keys.certificates.get(key_id) {
+        Some(cert) => json!({"kid": key_id, "cert": cert}),
+        None => json!({"kid": key_id}),
+    }
+}
+
+fn get_team_jwks(team_keys: &TeamKeys) -> Vec<jwk::Jwk> {
+    team_keys.keys.values().map(|key| key.get_jwk()).collect()
+}
+
+impl TeamKeys {
+    /// Exports the keys as an RFC 7517 JWKS document. Each key carries a `status`
+    /// member and the document carries the kid of the latest key in `latest_kid`.
+    pub fn to_jwks(&self) -> Value {
+        export_jwks(&self.latest_key_id, get_team_jwks(self))
+    }
+
+    /// Exports the keys as PEM encoded SubjectPublicKeyInfo structures,
+    /// latest key first. Keys whose parameters cannot be decoded are omitted.
+    pub fn to_pem(&self) -> Vec<PemKey> {
+        export_pem(&self.latest_key_id, get_team_jwks(self))
+    }
+
+    /// Exports the keys in the format of the CF API `/cdn-cgi/access/certs` endpoint,
+    /// including any certificates published alongside them.
+    pub fn to_certs_json(&self) -> Value {
+        let jwks = sort_jwks(&self.latest_key_id, get_team_jwks(self));
+
+        let mut key_ids: Vec<&String> = self.certificates.keys().collect();
+        key_ids.sort();
+
+        json!({
+            "keys": jwks,
+            "public_cert": get_public_cert(self, &self.latest_key_id),
+            "public_certs": key_ids
+                .into_iter()
+                .map(|key_id| get_public_cert(self, key_id))
+                .collect::<Vec<Value>>(),
+        })
+    }
+}
+
+impl Cache {
+    /// Exports the trusted keys as an RFC 7517 JWKS document, see `TeamKeys::to_jwks`.
+    pub fn to_jwks(&self) -> Value {
+        let (latest_key_id, jwks) = self.get_jwks();
+        export_jwks(&latest_key_id, jwks)
+    }
+
+    /// Exports the trusted keys as PEM encoded SubjectPublicKeyInfo structures,
+    /// see `TeamKeys::to_pem`.
+    pub fn to_pem(&self) -> Vec<PemKey> {
+        let (latest_key_id, jwks) = self.get_jwks();
+        export_pem(&latest_key_id, jwks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEAM_NAME: &str = "molten";
+    const STATIC_KEYS: &str = include_str!("../test_data/sample_signing_keys.json");
+    const EC_OKP_KEYS: &str = include_str!("../test_data/mock_ec_okp_signing_keys.json");
+    const LATEST_KEY_ID: &str = "a5ea8bd1b94cadf2a5f0f47dad188e6aafbcd28eeab2e71b11ddd96d9cc28c69";
+    const ADDITIONAL_KEY_ID: &str =
+        "1112fda21ace0ef9f8be527697e87970566d4e4dfab130d9a9d7a0748d3da8dd";
+
+    const LATEST_KEY_PEM: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAsRgEvCtIbDQ9gW+xFeKe
+lJZ2rALV+zwqXRtd+qsA78dVumeymnj6zRZBhqvXBOHl1HHuxtFRDOF+DyRm9nuE
+hXAchndVOL7WHQS0EDflb1wmrywramfjB/2BQ0l4/BrKCNwuK6fhd6bTk69HqkEb
+LJOzC8Jn6naTaZjbQBC6PqlqsXBGuitoGXAhg0AB+lBm3gjwFqxXPbwFZu3a0kdu
+JLMlV3s4TQPqd32upMMPSvqbQp32Q0O47Vg2iR1lLiBjxdWk5xnRlQC3t0Tp4+/k
+wO8PahdrE33g7ZBGwER349/8aIrR1tBlfMDC5oxxTB2GO2vWlpbbkBvkF27R7aYS
+FQIDAQAB
+-----END PUBLIC KEY-----
+";
+    const ES256_KEY_PEM: &str = "-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAECkHOy8x5MxuOj7Fue1QufnwnjF36
+Y4DjL0aeYqXSkJfsTf5TBgqrE1/ImdPEs7+t+pqeBOekq3fCMYfZ7Vk7ew==
+-----END PUBLIC KEY-----
+";
+    const ES384_KEY_PEM: &str = "-----BEGIN PUBLIC KEY-----
+MHYwEAYHKoZIzj0CAQYFK4EEACIDYgAE2kyJCw69P7wuTzRRVHXNlOElJYKMGCb6
+1K4oNWDTYlp3bmDBXv7O2sxCULjHREu1uE0VM3ZBs95Vm31FhkMCjwplVMMbyHGZ
+6kKOIwiQDXFMeFJPhz4s5tnUG9uIa13i
+-----END PUBLIC KEY-----
+";
+    const EDDSA_KEY_PEM: &str = "-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VwAyEAMiAZMwwGRBVNflOWva6LdKKeq+YduF1NcLOYqHj6UZI=
+-----END PUBLIC KEY-----
+";
+
+    #[test]
+    fn test_team_keys_to_jwks() {
+        let team_keys = TeamKeys::from_str(TEAM_NAME, STATIC_KEYS).unwrap();
+        let jwks = team_keys.to_jwks();
+
+        assert_eq!(jwks["latest_kid"], LATEST_KEY_ID);
+        assert_eq!(jwks["keys"][0]["kid"], LATEST_KEY_ID);
+        assert_eq!(jwks["keys"][0]["status"], "active");
+        assert_eq!(jwks["keys"][0]["kty"], "RSA");
+        assert_eq!(jwks["keys"][0]["alg"], "RS256");
+        assert_eq!(jwks["keys"][0]["use"], "sig");
+        assert_eq!(jwks["keys"][1]["kid"], ADDITIONAL_KEY_ID);
+        assert_eq!(jwks["keys"][1]["status"], "inactive");
+
+        // the exported document is a standard JWKS
+        let jwk_set: jwk::JwkSet = serde_json::from_value(jwks).unwrap();
+        assert!(jwk_set.find(ADDITIONAL_KEY_ID).is_some());
+    }
+
+    #[test]
+    fn test_team_keys_to_pem() {
+        let team_keys = TeamKeys::from_str(TEAM_NAME, STATIC_KEYS).unwrap();
+        let pem_keys = team_keys.to_pem();
+
+        assert_eq!(pem_keys.len(), 2);
+        assert_eq!(pem_keys[0].key_id, LATEST_KEY_ID);
+        assert_eq!(pem_keys[0].status, KeyStatus::Active);
+        assert_eq!(pem_keys[0].pem, LATEST_KEY_PEM);
+        assert_eq!(pem_keys[1].status, KeyStatus::Inactive);
+        assert!(pem_keys[0]
+            .to_string()
+            .starts_with(&format!("kid: {LATEST_KEY_ID}\nstatus: active\n-----BEGIN")));
+    }
+
+    #[test]
+    fn test_ec_okp_to_pem() {
+        let team_keys = TeamKeys::from_str(TEAM_NAME, EC_OKP_KEYS).unwrap();
+        let pem_keys = team_keys.to_pem();
+
+        let get_pem = |key_id: &str| {
+            pem_keys
+                .iter()
+                .find(|pem_key| pem_key.key_id == key_id)
+                .unwrap()
+                .pem
+                .clone()
+        };
+
+        assert_eq!(get_pem("mock-es256"), ES256_KEY_PEM);
+        assert_eq!(get_pem("mock-es384"), ES384_KEY_PEM);
+        assert_eq!(get_pem("mock-eddsa"), EDDSA_KEY_PEM);
+    }
+
+    #[test]
+    fn test_team_keys_to_certs_json() {
+        let team_keys = TeamKeys::from_str(TEAM_NAME, STATIC_KEYS).unwrap();
+        let payload = team_keys.to_certs_json();
+
+        assert_eq!(payload["public_cert"]["kid"], LATEST_KEY_ID);
+        assert_eq!(
+            payload["public_cert"]["cert"].as_str(),
+            team_keys
+                .certificates
+                .get(LATEST_KEY_ID)
+                .map(|cert| cert.as_str())
+        );
+        assert_eq!(payload["public_certs"].as_array().unwrap().len(), 2);
+
+        let reloaded = TeamKeys::from_json(TEAM_NAME, payload).unwrap();
+        assert_eq!(reloaded.latest_key_id, LATEST_KEY_ID);
+        assert_eq!(reloaded.keys.len(), 2);
+        assert_eq!(reloaded.certificates, team_keys.certificates);
+        assert_eq!(reloaded.to_jwks(), team_keys.to_jwks());
+    }
+
+    #[test]
+    fn test_cache_export() {
+        let team_keys = TeamKeys::from_str(TEAM_NAME, STATIC_KEYS).unwrap();
+        let jwks = team_keys.to_jwks();
+        let cache = Cache::new(&team_keys.latest_key_id, team_keys.keys);
+
+        assert_eq!(cache.to_jwks(), jwks);
+        assert_eq!(cache.to_pem()[0].pem, LATEST_KEY_PEM);
+    }
+}
@@ -0,0 +1,767 @@
+use crate::{
+    errors::{ValidationError, ValidationResult},
+    DecodedToken, Validator,
+};
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::{self, Future},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use jsonwebtoken::Validation;
+use tonic::{
+    body::Body,
+    codegen::{http, Body as HttpBody, Service, StdError},
+    server::{Grpc, NamedService, UnaryService},
+};
+use tonic_prost::ProstCodec;
+
+const SERVICE_NAME: &str = "envoy.service.auth.v3.Authorization";
+const CHECK_METHOD_PATH: &str = "/envoy.service.auth.v3.Authorization/Check";
+
+const TOKEN_HEADER: &str = "cf-access-jwt-assertion";
+const TOKEN_COOKIE: &str = "CF_Authorization";
+
+const TEAM_EXTENSION: &str = "cfzt_team";
+const AUDIENCE_EXTENSION: &str = "cfzt_audience";
+
+const UNAUTHENTICATED_MESSAGE: &str = "unauthenticated";
+// validation failures the client can resolve by presenting a fresh token
+const UNAUTHENTICATED_KINDS: [&str; 4] = [
+    "header_decode_failure",
+    "header_missing_kid",
+    "token_expired",
+    "token_not_yet_valid",
+];
+const DENIED_MESSAGE: &str = "access denied";
+
+const DEFAULT_CLAIM_HEADERS: [(&str, &str); 3] = [
+    ("email", "cf-access-authenticated-user-email"),
+    ("sub", "x-cfzt-subject"),
+    ("country", "x-cfzt-country"),
+];
+
+/// Hand-maintained subset of the Envoy ext_authz v3 protobuf messages.
+/// Only the fields consumed or emitted by `ExtAuthzServer` are declared,
+/// prost skips everything else on the wire.
+pub mod proto {
+    use std::collections::HashMap;
+
+    /// google.rpc.Code values used in `Status.code`.
+    pub const RPC_CODE_OK: i32 = 0;
+    pub const RPC_CODE_PERMISSION_DENIED: i32 = 7;
+    pub const RPC_CODE_UNAUTHENTICATED: i32 = 16;
+
+    /// envoy.config.core.v3.HeaderValueOption.HeaderAppendAction.OVERWRITE_IF_EXISTS_OR_ADD
+    pub const APPEND_ACTION_OVERWRITE_IF_EXISTS_OR_ADD: i32 = 2;
+
+    /// envoy.service.auth.v3.CheckRequest
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CheckRequest {
+        #[prost(message, optional, tag = "1")]
+        pub attributes: Option<AttributeContext>,
+    }
+
+    /// envoy.service.auth.v3.AttributeContext
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AttributeContext {
+        #[prost(message, optional, tag = "4")]
+        pub request: Option<Request>,
+        #[prost(map = "string, string", tag = "10")]
+        pub context_extensions: HashMap<String, String>,
+    }
+
+    /// envoy.service.auth.v3.AttributeContext.Request
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Request {
+        #[prost(message, optional, tag = "2")]
+        pub http: Option<HttpRequest>,
+    }
+
+    /// envoy.service.auth.v3.AttributeContext.HttpRequest
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HttpRequest {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(string, tag = "2")]
+        pub method: String,
+        #[prost(map = "string, string", tag = "3")]
+        pub headers: HashMap<String, String>,
+        #[prost(string, tag = "4")]
+        pub path: String,
+        #[prost(string, tag = "5")]
+        pub host: String,
+        #[prost(message, optional, tag = "13")]
+        pub header_map: Option<HeaderMap>,
+    }
+
+    /// envoy.config.core.v3.HeaderMap
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HeaderMap {
+        #[prost(message, repeated, tag = "1")]
+        pub headers: Vec<HeaderValue>,
+    }
+
+    /// envoy.config.core.v3.HeaderValue
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HeaderValue {
+        #[prost(string, tag = "1")]
+        pub key: String,
+        #[prost(string, tag = "2")]
+        pub value: String,
+        #[prost(bytes = "vec", tag = "3")]
+        pub raw_value: Vec<u8>,
+    }
+
+    /// envoy.config.core.v3.HeaderValueOption
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HeaderValueOption {
+        #[prost(message, optional, tag = "1")]
+        pub header: Option<HeaderValue>,
+        #[prost(int32, tag = "3")]
+        pub append_action: i32,
+    }
+
+    /// google.rpc.Status
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Status {
+        #[prost(int32, tag = "1")]
+        pub code: i32,
+        #[prost(string, tag = "2")]
+        pub message: String,
+    }
+
+    /// envoy.type.v3.HttpStatus
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HttpStatus {
+        #[prost(int32, tag = "1")]
+        pub code: i32,
+    }
+
+    /// envoy.service.auth.v3.DeniedHttpResponse
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DeniedHttpResponse {
+        #[prost(message, optional, tag = "1")]
+        pub status: Option<HttpStatus>,
+        #[prost(message, repeated, tag = "2")]
+        pub headers: Vec<HeaderValueOption>,
+        #[prost(string, tag = "3")]
+        pub body: String,
+    }
+
+    /// envoy.service.auth.v3.OkHttpResponse
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct OkHttpResponse {
+        #[prost(message, repeated, tag = "2")]
+        pub headers: Vec<HeaderValueOption>,
+        #[prost(string, repeated, tag = "5")]
+        pub headers_to_remove: Vec<String>,
+    }
+
+    /// envoy.service.auth.v3.CheckResponse
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CheckResponse {
+        #[prost(message, optional, tag = "1")]
+        pub status: Option<Status>,
+        #[prost(oneof = "HttpResponse", tags = "2, 3")]
+        pub http_response: Option<HttpResponse>,
+    }
+
+    /// envoy.service.auth.v3.CheckResponse.http_response
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum HttpResponse {
+        #[prost(message, tag = "2")]
+        DeniedResponse(DeniedHttpResponse),
+        #[prost(message, tag = "3")]
+        OkResponse(OkHttpResponse),
+    }
+}
+
+fn header_option(key: &str, value: &str) -> proto::HeaderValueOption {
+    proto::HeaderValueOption {
+        header: Some(proto::HeaderValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            raw_value: Vec::new(),
+        }),
+        append_action: proto::APPEND_ACTION_OVERWRITE_IF_EXISTS_OR_ADD,
+    }
+}
+
+fn get_header(http: &proto::HttpRequest, name: &str) -> Option<String> {
+    if let Some(value) = http.headers.get(name) {
+        return Some(value.clone());
+    }
+
+    // Envoy populates header_map instead of headers when raw header encoding is enabled
+    let header_map = http.header_map.as_ref()?;
+    let header = header_map
+        .headers
+        .iter()
+        .find(|hdr| hdr.key.eq_ignore_ascii_case(name))?;
+
+    if header.raw_value.is_empty() {
+        Some(header.value.clone())
+    } else {
+        String::from_utf8(header.raw_value.clone()).ok()
+    }
+}
+
+fn get_cookie(http: &proto::HttpRequest, name: &str) -> Option<String> {
+    get_header(http, "cookie")?
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+fn extract_token(http: &proto::HttpRequest) -> Option<String> {
+    get_header(http, TOKEN_HEADER).or_else(|| get_cookie(http, TOKEN_COOKIE))
+}
+
+fn ok_response(
+    headers: Vec<proto::HeaderValueOption>,
+    headers_to_remove: Vec<String>,
+) -> proto::CheckResponse {
+    proto::CheckResponse {
+        status: Some(proto::Status {
+            code: proto::RPC_CODE_OK,
+            message: String::new(),
+        }),
+        http_response: Some(proto::HttpResponse::OkResponse(proto::OkHttpResponse {
+            headers,
+            headers_to_remove,
+        })),
+    }
+}
+
+fn unauthenticated_response() -> proto::CheckResponse {
+    denied_response(
+        proto::RPC_CODE_UNAUTHENTICATED,
+        401,
+        UNAUTHENTICATED_MESSAGE,
+    )
+}
+
+fn denied_response(rpc_code: i32, http_code: i32, message: &str) -> proto::CheckResponse {
+    proto::CheckResponse {
+        status: Some(proto::Status {
+            code: rpc_code,
+            message: message.to_string(),
+        }),
+        http_response: Some(proto::HttpResponse::DeniedResponse(
+            proto::DeniedHttpResponse {
+                status: Some(proto::HttpStatus { code: http_code }),
+                headers: Vec::new(),
+                body: message.to_string(),
+            },
+        )),
+    }
+}
+
+/// An Envoy ext_authz v3 `Authorization` service backed by a Validator.
+///
+/// The token is read from the `Cf-Access-Jwt-Assertion` header, falling back
+/// to the `CF_Authorization` cookie. The team and audiences default to those
+/// configured on the server, and can be overridden per route with the
+/// `cfzt_team` and `cfzt_audience` (comma separated) context extensions.
+#[derive(Clone)]
+pub struct ExtAuthzServer {
+    validator: Arc<dyn Validator>,
+    team_name: Option<String>,
+    audiences: Vec<String>,
+    constraints: Validation,
+    claim_headers: Vec<(String, String)>,
+}
+
+impl ExtAuthzServer {
+    /// Constructs a new ExtAuthzServer with the default claim header mappings.
+    pub fn new(validator: Arc<dyn Validator>) -> Self {
+        ExtAuthzServer {
+            validator,
+            team_name: None,
+            audiences: Vec::new(),
+            constraints: Validation::new(jsonwebtoken::Algorithm::RS256),
+            claim_headers: DEFAULT_CLAIM_HEADERS
+                .iter()
+                .map(|(claim, header)| (claim.to_string(), header.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Sets the team used when a route does not provide a `cfzt_team` extension.
+    pub fn with_team(mut self, team_name: &str) -> Self {
+        self.team_name = Some(team_name.to_string());
+        self
+    }
+
+    /// Adds an audience used when a route does not provide a `cfzt_audience` extension.
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.audiences.push(audience.to_string());
+        self
+    }
+
+    /// Replaces the base constraints applied to every token.
+    /// The audience is always overwritten with the resolved audiences.
+    pub fn with_constraints(mut self, constraints: Validation) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Maps a string claim onto an upstream request header.
+    pub fn with_claim_header(mut self, claim: &str, header: &str) -> Self {
+        self.claim_headers
+            .push((claim.to_string(), header.to_ascii_lowercase()));
+        self
+    }
+
+    /// Removes all claim header mappings, including the defaults.
+    pub fn without_claim_headers(mut self) -> Self {
+        self.claim_headers.clear();
+        self
+    }
+
+    fn resolve_team_name(&self, extensions: &HashMap<String, String>) -> ValidationResult<String> {
+        extensions
+            .get(TEAM_EXTENSION)
+            .or(self.team_name.as_ref())
+            .cloned()
+            .ok_or(ValidationError::missing_team_name())
+    }
+
+    fn resolve_audiences(
+        &self,
+        extensions: &HashMap<String, String>,
+    ) -> ValidationResult<Vec<String>> {
+        let audiences: Vec<String> = match extensions.get(AUDIENCE_EXTENSION) {
+            Some(value) => value
+                .split(',')
+                .map(|aud| aud.trim().to_string())
+                .filter(|aud| !aud.is_empty())
+                .collect(),
+            None => self.audiences.clone(),
+        };
+
+        if audiences.is_empty() {
+            return Err(ValidationError::missing_audience());
+        }
+
+        Ok(audiences)
+    }
+
+    fn validate(
+        &self,
+        token: &str,
+        extensions: &HashMap<String, String>,
+    ) -> ValidationResult<DecodedToken> {
+        let team_name = self.resolve_team_name(extensions)?;
+        let audiences = self.resolve_audiences(extensions)?;
+
+        let mut constraints = self.constraints.clone();
+        constraints.set_audience(&audiences);
+
+        self.validator
+            .validate_token(token, &team_name, &mut constraints)
+    }
+
+    // every claim header is removed from the request, so that a client cannot
+    // supply its own value for a claim the token lacks, e.g. the email of a service token
+    fn get_claim_header_names(&self) -> Vec<String> {
+        self.claim_headers
+            .iter()
+            .map(|(_, header)| header.clone())
+            .collect()
+    }
+
+    fn build_claim_headers(&self, token_data: &DecodedToken) -> Vec<proto::HeaderValueOption> {
+        self.claim_headers
+            .iter()
+            .filter_map(|(claim, header)| {
+                let value = token_data.claims.get(claim)?.as_str()?;
+                Some(header_option(header, value))
+            })
+            .collect()
+    }
+
+    /// Evaluates a single CheckRequest, returning an OK response with the
+    /// mapped claim headers or a denied response. Missing, malformed, expired
+    /// and not yet valid tokens are denied with a 401, other failures with a 403.
+    /// Denied responses carry a generic message, so that validation details
+    /// are not disclosed to the client.
+    pub fn check(&self, request: &proto::CheckRequest) -> proto::CheckResponse {
+        let empty_extensions = HashMap::new();
+        let attributes = request.attributes.as_ref();
+        let extensions = attributes
+            .map(|attrs| &attrs.context_extensions)
+            .unwrap_or(&empty_extensions);
+        let http = attributes
+            .and_then(|attrs| attrs.request.as_ref())
+            .and_then(|req| req.http.as_ref());
+
+        let token = match http.and_then(extract_token) {
+            Some(token) => token,
+            None => return unauthenticated_response(),
+        };
+
+        match self.validate(&token, extensions) {
+            Ok(token_data) => ok_response(
+                self.build_claim_headers(&token_data),
+                self.get_claim_header_names(),
+            ),
+            Err(err) if UNAUTHENTICATED_KINDS.contains(&err.get_kind()) => {
+                unauthenticated_response()
+            }
+            Err(_) => denied_response(proto::RPC_CODE_PERMISSION_DENIED, 403, DENIED_MESSAGE),
+        }
+    }
+}
+
+struct CheckSvc(ExtAuthzServer);
+
+impl UnaryService<proto::CheckRequest> for CheckSvc {
+    type Response = proto::CheckResponse;
+    type Future = future::Ready<Result<tonic::Response<Self::Response>, tonic::Status>>;
+
+    fn call(&mut self, request: tonic::Request<proto::CheckRequest>) -> Self::Future {
+        future::ready(Ok(tonic::Response::new(self.0.check(request.get_ref()))))
+    }
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = Result<T, Infallible>> + Send + 'static>>;
+
+impl<B> Service<http::Request<B>> for ExtAuthzServer
+where
+    B: HttpBody + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if req.uri().path() != CHECK_METHOD_PATH {
+            return Box::pin(future::ready(Ok(
+                tonic::Status::unimplemented("").into_http()
+            )));
+        }
+
+        let method = CheckSvc(self.clone());
+
+        Box::pin(async move {
+            let mut grpc = Grpc::new(ProstCodec::default());
+            Ok(grpc.unary(method, req).await)
+        })
+    }
+}
+
+impl NamedService for ExtAuthzServer {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::FakeClock, testing::MockIssuer, MultiTeamValidator, TeamValidator};
+    use http_body_util::{BodyExt, Full};
+    use prost::Message;
+    use std::{io::Cursor, time::Duration};
+
+    const TEAM_NAME: &str = "molten";
+    const AUDIENCE: &str = "41f1d879c797d912d9bd80710db3dce92d30602a2dcbdf7bab33913071c44bd4";
+    const ISSUED_AT: u64 = 1717979639;
+
+    struct Fixture {
+        server: ExtAuthzServer,
+        issuer: MockIssuer,
+        clock: Arc<FakeClock>,
+    }
+
+    impl Fixture {
+        fn get_token(&self) -> String {
+            self.issuer.app_token(AUDIENCE).sign()
+        }
+    }
+
+    fn get_fixture() -> Fixture {
+        let clock = Arc::new(FakeClock::new(ISSUED_AT));
+        let issuer = MockIssuer::new(TEAM_NAME).with_clock(clock.clone());
+        let mut validator = MultiTeamValidator::default();
+        validator
+            .add_team(
+                TeamValidator::from_team_keys(issuer.to_team_keys()).with_clock(clock.clone()),
+            )
+            .unwrap();
+
+        let mut constraints = Validation::new(jsonwebtoken::Algorithm::RS256);
+        constraints.validate_nbf = true;
+        constraints.leeway = 0;
+
+        let server = ExtAuthzServer::new(Arc::new(validator))
+            .with_team(TEAM_NAME)
+            .with_audience(AUDIENCE)
+            .with_constraints(constraints);
+
+        Fixture {
+            server,
+            issuer,
+            clock,
+        }
+    }
+
+    // drives a future that never waits on I/O, such as a call to the gRPC service
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn get_request(headers: &[(&str, &str)], extensions: &[(&str, &str)]) -> proto::CheckRequest {
+        let to_map = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        };
+
+        proto::CheckRequest {
+            attributes: Some(proto::AttributeContext {
+                request: Some(proto::Request {
+                    http: Some(proto::HttpRequest {
+                        headers: to_map(headers),
+                        ..Default::default()
+                    }),
+                }),
+                context_extensions: to_map(extensions),
+            }),
+        }
+    }
+
+    fn get_denied_body(response: &proto::CheckResponse) -> String {
+        match response.http_response.as_ref().unwrap() {
+            proto::HttpResponse::DeniedResponse(denied) => denied.body.clone(),
+            proto::HttpResponse::OkResponse(_) => panic!("expected denied response"),
+        }
+    }
+
+    fn get_http_status(response: &proto::CheckResponse) -> i32 {
+        match response.http_response.as_ref().unwrap() {
+            proto::HttpResponse::DeniedResponse(denied) => denied.status.as_ref().unwrap().code,
+            proto::HttpResponse::OkResponse(_) => 200,
+        }
+    }
+
+    #[test]
+    fn test_check_header_token() {
+        let fixture = get_fixture();
+        let token = fixture.get_token();
+        let response = fixture
+            .server
+            .check(&get_request(&[(TOKEN_HEADER, &token)], &[]));
+
+        assert_eq!(response.status.as_ref().unwrap().code, proto::RPC_CODE_OK);
+
+        match response.http_response.unwrap() {
+            proto::HttpResponse::OkResponse(ok) => {
+                let headers: HashMap<String, String> = ok
+                    .headers
+                    .into_iter()
+                    .map(|opt| opt.header.unwrap())
+                    .map(|hdr| (hdr.key, hdr.value))
+                    .collect();
+
+                assert_eq!(
+                    headers["cf-access-authenticated-user-email"],
+                    "user@example.com"
+                );
+                assert_eq!(
+                    headers["x-cfzt-subject"],
+                    "00000000-0000-0000-0000-000000000000"
+                );
+                assert_eq!(headers["x-cfzt-country"], "AU");
+
+                let mut removed = ok.headers_to_remove;
+                removed.sort();
+                assert_eq!(
+                    removed,
+                    vec![
+                        "cf-access-authenticated-user-email",
+                        "x-cfzt-country",
+                        "x-cfzt-subject"
+                    ]
+                );
+            }
+            _ => panic!("expected ok response"),
+        }
+    }
+
+    #[test]
+    fn test_check_spoofed_claim_header() {
+        let fixture = get_fixture();
+        let token = fixture.get_token();
+        let server = fixture
+            .server
+            .with_claim_header("service_token_id", "x-cfzt-service-token");
+        let response = server.check(&get_request(
+            &[(TOKEN_HEADER, &token), ("x-cfzt-service-token", "spoofed")],
+            &[],
+        ));
+
+        match response.http_response.unwrap() {
+            proto::HttpResponse::OkResponse(ok) => {
+                assert!(ok
+                    .headers
+                    .iter()
+                    .all(|opt| opt.header.as_ref().unwrap().key != "x-cfzt-service-token"));
+                assert!(ok
+                    .headers_to_remove
+                    .contains(&"x-cfzt-service-token".to_string()));
+            }
+            _ => panic!("expected ok response"),
+        }
+    }
+
+    #[test]
+    fn test_check_cookie_token() {
+        let fixture = get_fixture();
+        let cookie = format!("foo=bar; {TOKEN_COOKIE}={}", fixture.get_token());
+        let response = fixture
+            .server
+            .check(&get_request(&[("cookie", &cookie)], &[]));
+
+        assert_eq!(get_http_status(&response), 200);
+    }
+
+    #[test]
+    fn test_check_missing_token() {
+        let fixture = get_fixture();
+        let response = fixture.server.check(&get_request(&[], &[]));
+
+        assert_eq!(
+            response.status.as_ref().unwrap().code,
+            proto::RPC_CODE_UNAUTHENTICATED
+        );
+        assert_eq!(get_http_status(&response), 401);
+        assert_eq!(get_denied_body(&response), UNAUTHENTICATED_MESSAGE);
+    }
+
+    #[test]
+    fn test_check_expired_token() {
+        let fixture = get_fixture();
+        let token = fixture.get_token();
+        let request = get_request(&[(TOKEN_HEADER, &token)], &[]);
+        assert_eq!(get_http_status(&fixture.server.check(&request)), 200);
+
+        fixture.clock.advance(Duration::from_secs(3601));
+        let response = fixture.server.check(&request);
+        assert_eq!(
+            response.status.as_ref().unwrap().code,
+            proto::RPC_CODE_UNAUTHENTICATED
+        );
+        assert_eq!(get_http_status(&response), 401);
+        assert_eq!(get_denied_body(&response), UNAUTHENTICATED_MESSAGE);
+    }
+
+    #[test]
+    fn test_check_context_extensions() {
+        let fixture = get_fixture();
+        let server = fixture.server.clone();
+        let token = fixture.get_token();
+
+        let response = server.check(&get_request(
+            &[(TOKEN_HEADER, &token)],
+            &[(AUDIENCE_EXTENSION, "foo, bar")],
+        ));
+        assert_eq!(
+            response.status.as_ref().unwrap().code,
+            proto::RPC_CODE_PERMISSION_DENIED
+        );
+        assert_eq!(get_http_status(&response), 403);
+        assert_eq!(get_denied_body(&response), DENIED_MESSAGE);
+
+        let response = server.check(&get_request(
+            &[(TOKEN_HEADER, &token)],
+            &[(TEAM_EXTENSION, "example")],
+        ));
+        assert_eq!(get_http_status(&response), 403);
+    }
+
+    fn call_service(
+        server: &mut ExtAuthzServer,
+        path: &str,
+        request: &proto::CheckRequest,
+    ) -> (http::Response<Body>, Vec<u8>, Option<http::HeaderMap>) {
+        // gRPC length-prefixed message framing, uncompressed
+        let message = request.encode_to_vec();
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&message);
+
+        let request = http::Request::builder()
+            .method("POST")
+            .uri(format!("http://localhost{path}"))
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .body(Full::new(Cursor::new(frame)))
+            .unwrap();
+
+        let (parts, body) = block_on(server.call(request)).unwrap().into_parts();
+        let collected = block_on(body.collect()).unwrap();
+        let trailers = collected.trailers().cloned();
+        let bytes = collected.to_bytes().to_vec();
+
+        (
+            http::Response::from_parts(parts, Body::empty()),
+            bytes,
+            trailers,
+        )
+    }
+
+    #[test]
+    fn test_grpc_service() {
+        let fixture = get_fixture();
+        let token = fixture.get_token();
+        let mut server = fixture.server;
+        assert_eq!(
+            <ExtAuthzServer as NamedService>::NAME,
+            "envoy.service.auth.v3.Authorization"
+        );
+
+        let request = get_request(&[(TOKEN_HEADER, &token)], &[]);
+        let (response, bytes, trailers) = call_service(&mut server, CHECK_METHOD_PATH, &request);
+        assert_eq!(response.status(), 200);
+        assert_eq!(trailers.unwrap()["grpc-status"], "0");
+
+        let length = u32::from_be_bytes(bytes[1..5].try_into().unwrap()) as usize;
+        assert_eq!(bytes[0], 0);
+        assert_eq!(bytes.len(), 5 + length);
+        let response = proto::CheckResponse::decode(&bytes[5..]).unwrap();
+        assert_eq!(response, server.check(&request));
+        assert_eq!(get_http_status(&response), 200);
+
+        let request = get_request(&[], &[]);
+        let (_, bytes, _) = call_service(&mut server, CHECK_METHOD_PATH, &request);
+        let response = proto::CheckResponse::decode(&bytes[5..]).unwrap();
+        assert_eq!(get_http_status(&response), 401);
+
+        let (response, _, _) = call_service(
+            &mut server,
+            "/envoy.service.auth.v3.Authorization/Other",
+            &request,
+        );
+        let grpc_status = response.headers()["grpc-status"].to_str().unwrap();
+        assert_eq!(grpc_status, (tonic::Code::Unimplemented as i32).to_string());
+    }
+}
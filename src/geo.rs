@@ -0,0 +1,179 @@
+use crate::{
+    errors::{ValidationError, ValidationResult},
+    DecodedToken,
+};
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+fn normalise_countries(countries: &[&str]) -> HashSet<String> {
+    countries
+        .iter()
+        .map(|country| country.trim().to_uppercase())
+        .collect()
+}
+
+/// Extracts the `aud` claim, which may be either a string or an array of strings.
+pub(crate) fn get_audiences(token_data: &DecodedToken) -> Vec<String> {
+    match token_data.claims.get("aud") {
+        Some(Value::String(aud)) => vec![aud.clone()],
+        Some(Value::Array(auds)) => auds
+            .iter()
+            .filter_map(|aud| aud.as_str())
+            .map(|aud| aud.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A geographic restriction applied to the `country` claim of a token.
+/// Country codes are ISO-3166 alpha-2 and compared case-insensitively.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CountryRestriction {
+    /// Only tokens issued in one of the listed countries are accepted.
+    Allow(HashSet<String>),
+    /// Tokens issued in any of the listed countries are rejected.
+    /// Tokens without a `country` claim are accepted unless `reject_missing` is set.
+    Deny {
+        countries: HashSet<String>,
+        reject_missing: bool,
+    },
+}
+
+impl CountryRestriction {
+    /// Constructs an allowlist restriction from a set of country codes.
+    pub fn allow(countries: &[&str]) -> Self {
+        CountryRestriction::Allow(normalise_countries(countries))
+    }
+
+    /// Constructs a denylist restriction from a set of country codes.
+    pub fn deny(countries: &[&str]) -> Self {
+        CountryRestriction::Deny {
+            countries: normalise_countries(countries),
+            reject_missing: false,
+        }
+    }
+
+    /// Rejects tokens without a `country` claim under a denylist,
+    /// as an allowlist always does.
+    pub fn reject_missing_country(self) -> Self {
+        match self {
+            CountryRestriction::Deny { countries, .. } => CountryRestriction::Deny {
+                countries,
+                reject_missing: true,
+            },
+            allow => allow,
+        }
+    }
+
+    /// Returns true if a token issued in the given country is permitted.
+    pub fn is_permitted(&self, country: &str) -> bool {
+        let country = country.trim().to_uppercase();
+
+        match self {
+            CountryRestriction::Allow(countries) => countries.contains(&country),
+            CountryRestriction::Deny { countries, .. } => !countries.contains(&country),
+        }
+    }
+
+    /// Checks the `country` claim of a validated token for a given audience.
+    /// Tokens without a `country` claim, or with a blank one, are rejected by an allowlist
+    /// and only rejected by a denylist if `reject_missing_country` was set.
+    pub fn check(&self, token_data: &DecodedToken, audience: &str) -> ValidationResult<()> {
+        let country = token_data
+            .claims
+            .get("country")
+            .and_then(|country| country.as_str())
+            .map(|country| country.trim())
+            .filter(|country| !country.is_empty());
+
+        let Some(country) = country else {
+            return match self {
+                CountryRestriction::Deny {
+                    reject_missing: false,
+                    ..
+                } => Ok(()),
+                _ => Err(ValidationError::missing_country(audience)),
+            };
+        };
+
+        match self.is_permitted(country) {
+            true => Ok(()),
+            false => Err(ValidationError::country_not_permitted(country, audience)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const AUDIENCE: &str = "foo";
+
+    fn get_token_data(claims: Value) -> DecodedToken {
+        DecodedToken {
+            header: jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            claims,
+        }
+    }
+
+    #[test]
+    fn test_country_allow() {
+        let restriction = CountryRestriction::allow(&["au", "NZ"]);
+
+        assert!(restriction.is_permitted("AU"));
+        assert!(restriction.is_permitted("nz"));
+        assert!(!restriction.is_permitted("US"));
+    }
+
+    #[test]
+    fn test_country_deny() {
+        let restriction = CountryRestriction::deny(&["KP"]);
+
+        assert!(restriction.is_permitted("AU"));
+        assert!(!restriction.is_permitted("kp"));
+    }
+
+    #[test]
+    fn test_country_check() {
+        let restriction = CountryRestriction::deny(&["KP"]);
+
+        let permitted = get_token_data(json!({"country": "AU"}));
+        assert!(restriction.check(&permitted, AUDIENCE).is_ok());
+
+        let denied = get_token_data(json!({"country": "KP"}));
+        let err = restriction.check(&denied, AUDIENCE).unwrap_err();
+        assert!(err.to_string().contains("'KP'"));
+
+        let padded = get_token_data(json!({"country": " kp "}));
+        assert!(restriction.check(&padded, AUDIENCE).is_err());
+
+        // service tokens carry no country, which only a denylist accepts by default
+        let missing = get_token_data(json!({"common_name": "ci.access"}));
+        assert!(restriction.check(&missing, AUDIENCE).is_ok());
+        let blank = get_token_data(json!({"country": " "}));
+        assert!(restriction.check(&blank, AUDIENCE).is_ok());
+
+        let restriction = restriction.reject_missing_country();
+        assert!(restriction.check(&permitted, AUDIENCE).is_ok());
+        let err = restriction.check(&missing, AUDIENCE).unwrap_err();
+        assert_eq!(err.get_kind(), "missing_country");
+        assert!(restriction.check(&blank, AUDIENCE).is_err());
+
+        let restriction = CountryRestriction::allow(&["AU"]);
+        assert!(restriction.check(&missing, AUDIENCE).is_err());
+        let padded = get_token_data(json!({"country": "au "}));
+        assert!(restriction.check(&padded, AUDIENCE).is_ok());
+    }
+
+    #[test]
+    fn test_get_audiences() {
+        let single = get_token_data(json!({"aud": "foo"}));
+        assert_eq!(get_audiences(&single), vec!["foo"]);
+
+        let many = get_token_data(json!({"aud": ["foo", "bar"]}));
+        assert_eq!(get_audiences(&many), vec!["foo", "bar"]);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    der::{decode_b64, strip_leading_zeros},
+    errors::{UnpackError, UnpackResult, ValidationError, ValidationResult},
+    keys::AccessKey,
+};
+
+use std::{collections::HashSet, str::FromStr};
+
+use jsonwebtoken::{jwk, Algorithm, Header};
+
+const DEFAULT_MIN_RSA_BITS: usize = 2048;
+
+fn get_rsa_bits(params: &jwk::RSAKeyParameters) -> Option<usize> {
+    let modulus = decode_b64(&params.n)?;
+    let modulus = strip_leading_zeros(&modulus);
+    let first = modulus.first()?;
+    Some(modulus.len() * 8 - first.leading_zeros() as usize)
+}
+
+// EC and OKP keys can only be used with the algorithm of their curve
+fn curve_matches(jwk: &jwk::Jwk, alg: jwk::KeyAlgorithm) -> bool {
+    match (&jwk.algorithm, alg) {
+        (jwk::AlgorithmParameters::EllipticCurve(params), jwk::KeyAlgorithm::ES256) => {
+            params.curve == jwk::EllipticCurve::P256
+        }
+        (jwk::AlgorithmParameters::EllipticCurve(params), jwk::KeyAlgorithm::ES384) => {
+            params.curve == jwk::EllipticCurve::P384
+        }
+        (jwk::AlgorithmParameters::OctetKeyPair(params), jwk::KeyAlgorithm::EdDSA) => {
+            params.curve == jwk::EllipticCurve::Ed25519
+        }
+        (
+            jwk::AlgorithmParameters::EllipticCurve(_) | jwk::AlgorithmParameters::OctetKeyPair(_),
+            _,
+        ) => false,
+        _ => true,
+    }
+}
+
+fn to_key_algorithm(alg: Algorithm) -> Option<jwk::KeyAlgorithm> {
+    jwk::KeyAlgorithm::from_str(&format!("{alg:?}")).ok()
+}
+
+/// Describes which signing keys are acceptable, applied when keys are
+/// ingested and again when a token is validated against a key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyPolicy {
+    min_rsa_bits: usize,
+    require_signature_use: bool,
+    require_matching_alg: bool,
+    allowed_algorithms: HashSet<jwk::KeyAlgorithm>,
+}
+
+impl Default for KeyPolicy {
+    /// Requires 2048 bit RSA moduli, `use: sig`, a supported algorithm
+    /// and a token header `alg` matching the JWK `alg`.
+    fn default() -> Self {
+        KeyPolicy {
+            min_rsa_bits: DEFAULT_MIN_RSA_BITS,
+            require_signature_use: true,
+            require_matching_alg: true,
+            allowed_algorithms: HashSet::from([
+                jwk::KeyAlgorithm::RS256,
+                jwk::KeyAlgorithm::RS384,
+                jwk::KeyAlgorithm::RS512,
+                jwk::KeyAlgorithm::ES256,
+                jwk::KeyAlgorithm::ES384,
+                jwk::KeyAlgorithm::EdDSA,
+            ]),
+        }
+    }
+}
+
+impl KeyPolicy {
+    /// Sets the minimum size of RSA moduli, in bits.
+    pub fn with_min_rsa_bits(mut self, bits: usize) -> Self {
+        self.min_rsa_bits = bits;
+        self
+    }
+
+    /// Replaces the set of acceptable key algorithms.
+    pub fn with_allowed_algorithms(mut self, algorithms: &[jwk::KeyAlgorithm]) -> Self {
+        self.allowed_algorithms = algorithms.iter().copied().collect();
+        self
+    }
+
+    /// Accepts keys regardless of their `use` parameter.
+    pub fn allow_any_use(mut self) -> Self {
+        self.require_signature_use = false;
+        self
+    }
+
+    /// Accepts tokens whose header `alg` differs from the JWK `alg`.
+    pub fn allow_alg_mismatch(mut self) -> Self {
+        self.require_matching_alg = false;
+        self
+    }
+
+    fn get_violation(&self, jwk: &jwk::Jwk) -> Option<String> {
+        if self.require_signature_use
+            && jwk.common.public_key_use != Some(jwk::PublicKeyUse::Signature)
+        {
+            return Some("key use is not 'sig'".to_string());
+        }
+
+        match jwk.common.key_algorithm {
+            Some(alg) if !self.allowed_algorithms.contains(&alg) => {
+                return Some(format!("alg '{alg}' is not allowed"))
+            }
+            Some(alg) if !curve_matches(jwk, alg) => {
+                return Some(format!("alg '{alg}' does not match the key curve"))
+            }
+            Some(_) => {}
+            None => return Some("key has no alg".to_string()),
+        }
+
+        if let jwk::AlgorithmParameters::RSA(params) = &jwk.algorithm {
+            match get_rsa_bits(params) {
+                Some(bits) if bits >= self.min_rsa_bits => {}
+                Some(bits) => {
+                    return Some(format!(
+                        "{bits} bit modulus is below the {} bit minimum",
+                        self.min_rsa_bits
+                    ))
+                }
+                None => return Some("modulus could not be decoded".to_string()),
+            }
+        }
+
+        None
+    }
+
+    /// Checks a key against the policy as it is ingested.
+    pub fn check_key(&self, access_key: &dyn AccessKey) -> UnpackResult<()> {
+        match self.get_violation(&access_key.get_jwk()) {
+            Some(reason) => Err(UnpackError::invalid_key(&access_key.get_key_id(), &reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks a key, and the header of the token it is about to verify, against the policy.
+    pub fn check_token_header(&self, header: &Header, jwk: &jwk::Jwk) -> ValidationResult<()> {
+        let key_id = jwk.common.key_id.clone().unwrap_or_default();
+
+        if let Some(reason) = self.get_violation(jwk) {
+            return Err(ValidationError::key_policy_violation(&key_id, &reason));
+        }
+
+        if self.require_matching_alg && to_key_algorithm(header.alg) != jwk.common.key_algorithm {
+            return Err(ValidationError::key_policy_violation(
+                &key_id,
+                &format!("token alg '{:?}' does not match key alg", header.alg),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{OkpAccessKey, RsaAccessKey};
+
+    const SAMPLE_PAYLOAD: &str = include_str!("../test_data/sample_signing_keys.json");
+    const EC_OKP_PAYLOAD: &str = include_str!("../test_data/mock_ec_okp_signing_keys.json");
+    // 512 bit modulus
+    const WEAK_MODULUS: &str =
+        "sXchDaQebHnPiGvyDOAT4saGEUetSyo9MKLOoWFsueri23bOdgWp4Dy1WlUzewbgBHod5pcM9H95GQRV3JDXbw";
+
+    fn get_rsa_key(alg: &str, usage: &str, modulus: &str) -> RsaAccessKey {
+        RsaAccessKey::new("mock-rsa", alg, usage, "AQAB", modulus)
+    }
+
+    fn get_sample_modulus() -> String {
+        let payload: serde_json::Value = serde_json::from_str(SAMPLE_PAYLOAD).unwrap();
+        payload["keys"][0]["n"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_check_key() {
+        let policy = KeyPolicy::default();
+        let modulus = get_sample_modulus();
+
+        assert!(policy
+            .check_key(&get_rsa_key("RS256", "sig", &modulus))
+            .is_ok());
+        assert!(policy
+            .check_key(&get_rsa_key("RS256", "enc", &modulus))
+            .is_err());
+        assert!(policy
+            .check_key(&get_rsa_key("HS256", "sig", &modulus))
+            .is_err());
+        assert!(policy
+            .clone()
+            .allow_any_use()
+            .check_key(&get_rsa_key("RS256", "enc", &modulus))
+            .is_ok());
+
+        let policy = KeyPolicy::default().with_allowed_algorithms(&[jwk::KeyAlgorithm::EdDSA]);
+        let key = OkpAccessKey::new(
+            "mock-eddsa",
+            "EdDSA",
+            "sig",
+            jwk::EllipticCurve::Ed25519,
+            "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo",
+        );
+        assert!(policy.check_key(&key).is_ok());
+        assert!(policy
+            .check_key(&get_rsa_key("RS256", "sig", &modulus))
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_key_curve() {
+        let policy = KeyPolicy::default();
+        let team_keys = crate::api::TeamKeys::from_str("molten", EC_OKP_PAYLOAD).unwrap();
+        for key in team_keys.keys.values() {
+            assert!(policy.check_key(key.as_ref()).is_ok());
+        }
+
+        let mut payload: serde_json::Value = serde_json::from_str(EC_OKP_PAYLOAD).unwrap();
+        for key in payload["keys"].as_array_mut().unwrap() {
+            let alg = if key["alg"] == "ES256" {
+                "ES384"
+            } else {
+                "ES256"
+            };
+            key["alg"] = serde_json::json!(alg);
+            let access_key = crate::api::extract_access_key(key).unwrap();
+            assert!(policy.check_key(access_key.as_ref()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_check_key_rsa_bits() {
+        let strong = get_rsa_key("RS256", "sig", &get_sample_modulus());
+        let weak = get_rsa_key("RS256", "sig", WEAK_MODULUS);
+
+        assert!(KeyPolicy::default().check_key(&weak).is_err());
+        assert!(KeyPolicy::default()
+            .with_min_rsa_bits(512)
+            .check_key(&weak)
+            .is_ok());
+        assert!(KeyPolicy::default()
+            .with_min_rsa_bits(2049)
+            .check_key(&strong)
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_token_header() {
+        let policy = KeyPolicy::default();
+        let jwk = get_rsa_key("RS256", "sig", &get_sample_modulus()).get_jwk();
+
+        assert!(policy
+            .check_token_header(&Header::new(Algorithm::RS256), &jwk)
+            .is_ok());
+        assert!(policy
+            .check_token_header(&Header::new(Algorithm::RS512), &jwk)
+            .is_err());
+        assert!(policy
+            .clone()
+            .allow_alg_mismatch()
+            .check_token_header(&Header::new(Algorithm::RS512), &jwk)
+            .is_ok());
+    }
+}
@@ -6,10 +6,41 @@ fn parse_alg(alg: &str) -> Option<jwk::KeyAlgorithm> {
         "RS256" => Some(jwk::KeyAlgorithm::RS256),
         "RS384" => Some(jwk::KeyAlgorithm::RS384),
         "RS512" => Some(jwk::KeyAlgorithm::RS512),
+        "ES256" => Some(jwk::KeyAlgorithm::ES256),
+        "ES384" => Some(jwk::KeyAlgorithm::ES384),
+        "EDDSA" => Some(jwk::KeyAlgorithm::EdDSA),
         _ => None,
     }
 }
 
+/// Maps a JWK `crv` value onto a curve supported by jsonwebtoken.
+pub(crate) fn parse_curve(curve: &str) -> Option<jwk::EllipticCurve> {
+    match curve {
+        "P-256" => Some(jwk::EllipticCurve::P256),
+        "P-384" => Some(jwk::EllipticCurve::P384),
+        "P-521" => Some(jwk::EllipticCurve::P521),
+        "Ed25519" => Some(jwk::EllipticCurve::Ed25519),
+        _ => None,
+    }
+}
+
+fn build_common_parameters(
+    key_id: &str,
+    key_algorithm: &str,
+    key_usage: &str,
+) -> jwk::CommonParameters {
+    jwk::CommonParameters {
+        public_key_use: Some(parse_usage(key_usage)),
+        key_operations: None,
+        key_algorithm: parse_alg(key_algorithm),
+        key_id: Some(key_id.to_string()),
+        x509_url: None,
+        x509_chain: None,
+        x509_sha1_fingerprint: None,
+        x509_sha256_fingerprint: None,
+    }
+}
+
 fn parse_usage(usage: &str) -> jwk::PublicKeyUse {
     match usage.to_lowercase().as_str() {
         "sig" => jwk::PublicKeyUse::Signature,
@@ -65,16 +96,7 @@ impl AccessKey for RsaAccessKey {
     /// RsaAccessKey.
     fn get_jwk(&self) -> jwk::Jwk {
         jwk::Jwk {
-            common: jwk::CommonParameters {
-                public_key_use: Some(parse_usage(&self.key_usage)),
-                key_operations: None,
-                key_algorithm: parse_alg(&self.key_algorithm),
-                key_id: Some(self.key_id.clone()),
-                x509_url: None,
-                x509_chain: None,
-                x509_sha1_fingerprint: None,
-                x509_sha256_fingerprint: None,
-            },
+            common: build_common_parameters(&self.key_id, &self.key_algorithm, &self.key_usage),
             algorithm: jwk::AlgorithmParameters::RSA(jwk::RSAKeyParameters {
                 key_type: jwk::RSAKeyType::RSA,
                 n: self.modulus.clone(),
@@ -84,6 +106,106 @@ impl AccessKey for RsaAccessKey {
     }
 }
 
+/// A struct representing an elliptic curve (`kty: EC`) public key used to sign CFZT JWTs.
+pub struct EcAccessKey {
+    key_id: String,
+    key_algorithm: String,
+    key_usage: String,
+    curve: jwk::EllipticCurve,
+    x: String,
+    y: String,
+}
+
+impl EcAccessKey {
+    /// Constructs a new EcAccessKey struct.
+    pub fn new(
+        key_id: &str,
+        key_algorithm: &str,
+        key_usage: &str,
+        curve: jwk::EllipticCurve,
+        x: &str,
+        y: &str,
+    ) -> Self {
+        EcAccessKey {
+            key_id: key_id.to_string(),
+            key_algorithm: key_algorithm.to_string(),
+            key_usage: key_usage.to_string(),
+            curve,
+            x: x.to_string(),
+            y: y.to_string(),
+        }
+    }
+}
+
+impl AccessKey for EcAccessKey {
+    /// Returns the key ID for the CFZT key.
+    fn get_key_id(&self) -> String {
+        self.key_id.clone()
+    }
+
+    /// Mints a valid jsonwebtoken::jwk::JWK struct for the
+    /// EcAccessKey.
+    fn get_jwk(&self) -> jwk::Jwk {
+        jwk::Jwk {
+            common: build_common_parameters(&self.key_id, &self.key_algorithm, &self.key_usage),
+            algorithm: jwk::AlgorithmParameters::EllipticCurve(jwk::EllipticCurveKeyParameters {
+                key_type: jwk::EllipticCurveKeyType::EC,
+                curve: self.curve.clone(),
+                x: self.x.clone(),
+                y: self.y.clone(),
+            }),
+        }
+    }
+}
+
+/// A struct representing an octet key pair (`kty: OKP`) public key used to sign CFZT JWTs.
+pub struct OkpAccessKey {
+    key_id: String,
+    key_algorithm: String,
+    key_usage: String,
+    curve: jwk::EllipticCurve,
+    x: String,
+}
+
+impl OkpAccessKey {
+    /// Constructs a new OkpAccessKey struct.
+    pub fn new(
+        key_id: &str,
+        key_algorithm: &str,
+        key_usage: &str,
+        curve: jwk::EllipticCurve,
+        x: &str,
+    ) -> Self {
+        OkpAccessKey {
+            key_id: key_id.to_string(),
+            key_algorithm: key_algorithm.to_string(),
+            key_usage: key_usage.to_string(),
+            curve,
+            x: x.to_string(),
+        }
+    }
+}
+
+impl AccessKey for OkpAccessKey {
+    /// Returns the key ID for the CFZT key.
+    fn get_key_id(&self) -> String {
+        self.key_id.clone()
+    }
+
+    /// Mints a valid jsonwebtoken::jwk::JWK struct for the
+    /// OkpAccessKey.
+    fn get_jwk(&self) -> jwk::Jwk {
+        jwk::Jwk {
+            common: build_common_parameters(&self.key_id, &self.key_algorithm, &self.key_usage),
+            algorithm: jwk::AlgorithmParameters::OctetKeyPair(jwk::OctetKeyPairParameters {
+                key_type: jwk::OctetKeyPairType::OctetKeyPair,
+                curve: self.curve.clone(),
+                x: self.x.clone(),
+            }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +216,10 @@ mod tests {
     const RSA_EXPONENT: &str = "AQAB";
     const RSA_MODULUS: &str = "xbd6OnCJ3D7mb3bjV0LXP9KeZ1Kuiuk0f_OIOi3S4KHunA1KYuUcmKfB5rAYKZakPvZ0YnmL7_GPZNd263fg55U6-QZsNKWW1sldHHCws-eEnyO6nc6KTk6rNwGUY0c2lfAOmyHmXPomy3ly6TBgXFfVs6N-O-LIIQDimaC8C6QHcYk7KBg_heDJG_Izzicb0YeYSOYYAcZ194fA3eU9WIpT3P4K4pMtoTDF4zE5GPDgm-ngnP2RViS0uBJcYXVHeAmAdA1gS5cbvw1tvxe73LZ461W37z2tJZwqapMnlbggnw8gIqy6--rBsKiSDfyfA86YSyUyC43UPWk50jupVw";
 
+    const EC_X: &str = "CkHOy8x5MxuOj7Fue1QufnwnjF36Y4DjL0aeYqXSkJc";
+    const EC_Y: &str = "7E3-UwYKqxNfyJnTxLO_rfqangTnpKt3wjGH2e1ZO3s";
+    const OKP_X: &str = "MiAZMwwGRBVNflOWva6LdKKeq-YduF1NcLOYqHj6UZI";
+
     #[test]
     fn test_rsa_access_key() {
         let key = RsaAccessKey::new(
@@ -124,4 +250,58 @@ mod tests {
             _ => panic!("unexpected AlgorithmParameters value"),
         }
     }
+
+    #[test]
+    fn test_ec_access_key() {
+        let key = EcAccessKey::new(
+            KEY_ID,
+            "ES256",
+            RSA_KEY_USAGE,
+            jwk::EllipticCurve::P256,
+            EC_X,
+            EC_Y,
+        );
+
+        assert_eq!(key.get_key_id(), KEY_ID);
+
+        let jwk = key.get_jwk();
+
+        assert_eq!(jwk.common.key_algorithm.unwrap(), jwk::KeyAlgorithm::ES256);
+
+        match jwk.algorithm {
+            jwk::AlgorithmParameters::EllipticCurve(params) => {
+                assert_eq!(params.key_type, jwk::EllipticCurveKeyType::EC);
+                assert_eq!(params.curve, jwk::EllipticCurve::P256);
+                assert_eq!(params.x, EC_X);
+                assert_eq!(params.y, EC_Y);
+            }
+            _ => panic!("unexpected AlgorithmParameters value"),
+        }
+    }
+
+    #[test]
+    fn test_okp_access_key() {
+        let key = OkpAccessKey::new(
+            KEY_ID,
+            "EdDSA",
+            RSA_KEY_USAGE,
+            jwk::EllipticCurve::Ed25519,
+            OKP_X,
+        );
+
+        assert_eq!(key.get_key_id(), KEY_ID);
+
+        let jwk = key.get_jwk();
+
+        assert_eq!(jwk.common.key_algorithm.unwrap(), jwk::KeyAlgorithm::EdDSA);
+
+        match jwk.algorithm {
+            jwk::AlgorithmParameters::OctetKeyPair(params) => {
+                assert_eq!(params.key_type, jwk::OctetKeyPairType::OctetKeyPair);
+                assert_eq!(params.curve, jwk::EllipticCurve::Ed25519);
+                assert_eq!(params.x, OKP_X);
+            }
+            _ => panic!("unexpected AlgorithmParameters value"),
+        }
+    }
 }
@@ -0,0 +1,248 @@
+use crate::{errors::ValidationResult, DecodedToken, StdResult};
+
+use std::time::Duration;
+
+use ::metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit,
+};
+
+/// Counts validations by `team`, `audience` and `outcome`, which is `ok` or the kind of ValidationError.
+pub const VALIDATIONS_TOTAL: &str = "cfzt_validations_total";
+/// Observes the duration of validations by `team` and `outcome`.
+pub const VALIDATION_DURATION_SECONDS: &str = "cfzt_validation_duration_seconds";
+/// Counts DecodingKey lookups by `result`: `hit`, `miss` (built from the JWK) or `unknown` (untrusted kid).
+pub const DECODING_KEY_LOOKUPS_TOTAL: &str = "cfzt_decoding_key_lookups_total";
+/// Counts syncs by `team` and `outcome`: `success` or `failure`.
+pub const SYNCS_TOTAL: &str = "cfzt_syncs_total";
+/// Counts applied key rotations by `team`.
+pub const KEY_ROTATIONS_TOTAL: &str = "cfzt_key_rotations_total";
+/// The time since the keys of a `team` were loaded or last confirmed by a sync.
+pub const KEY_SET_AGE_SECONDS: &str = "cfzt_key_set_age_seconds";
+
+/// Registers descriptions of the metrics with the installed recorder,
+/// e.g. a `metrics-exporter-prometheus` PrometheusRecorder. Metrics are recorded without it.
+pub fn describe() {
+    describe_counter!(
+        VALIDATIONS_TOTAL,
+        "Token validations by team, audience and outcome"
+    );
+    describe_histogram!(
+        VALIDATION_DURATION_SECONDS,
+        Unit::Seconds,
+        "Duration of token validations"
+    );
+    describe_counter!(
+        DECODING_KEY_LOOKUPS_TOTAL,
+        "DecodingKey cache lookups by result"
+    );
+    describe_counter!(SYNCS_TOTAL, "Key syncs by team and outcome");
+    describe_counter!(KEY_ROTATIONS_TOTAL, "Applied key rotations by team");
+    describe_gauge!(
+        KEY_SET_AGE_SECONDS,
+        Unit::Seconds,
+        "Time since the keys were loaded or last confirmed by a sync"
+    );
+}
+
+fn get_audience_label(constraints: &jsonwebtoken::Validation) -> String {
+    let mut audiences: Vec<&str> = constraints
+        .aud
+        .iter()
+        .flatten()
+        .map(|aud| aud.as_str())
+        .collect();
+    audiences.sort();
+    audiences.join(",")
+}
+
+pub(crate) fn record_validation(
+    team_name: &str,
+    constraints: &jsonwebtoken::Validation,
+    result: &ValidationResult<DecodedToken>,
+    elapsed: Duration,
+) {
+    let outcome = match result {
+        Ok(_) => "ok",
+        Err(err) => err.get_kind(),
+    };
+
+    counter!(
+        VALIDATIONS_TOTAL,
+        "team" => team_name.to_string(),
+        "audience" => get_audience_label(constraints),
+        "outcome" => outcome,
+    )
+    .increment(1);
+    histogram!(
+        VALIDATION_DURATION_SECONDS,
+        "team" => team_name.to_string(),
+        "outcome" => outcome,
+    )
+    .record(elapsed.as_secs_f64());
+}
+
+pub(crate) fn record_decoding_key_lookup(result: &'static str) {
+    counter!(DECODING_KEY_LOOKUPS_TOTAL, "result" => result).increment(1);
+}
+
+pub(crate) fn record_sync(team_name: &str, result: &StdResult<bool>) {
+    let outcome = match result {
+        Ok(_) => "success",
+        Err(_) => "failure",
+    };
+
+    counter!(SYNCS_TOTAL, "team" => team_name.to_string(), "outcome" => outcome).increment(1);
+}
+
+pub(crate) fn record_rotation(team_name: &str) {
+    counter!(KEY_ROTATIONS_TOTAL, "team" => team_name.to_string()).increment(1);
+}
+
+pub(crate) fn record_key_age(team_name: &str, age: Duration) {
+    gauge!(KEY_SET_AGE_SECONDS, "team" => team_name.to_string()).set(age.as_secs_f64());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::TeamKeys, clock::FakeClock, testing::MockIssuer, TeamValidator, Validator};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use std::{collections::HashMap, sync::Arc};
+
+    const TEAM_NAME: &str = "molten";
+    const AUDIENCE: &str = "mock-audience";
+
+    type Snapshot = HashMap<(String, Vec<(String, String)>), DebugValue>;
+
+    fn take_snapshot(recorder: &DebuggingRecorder) -> Snapshot {
+        recorder
+            .snapshotter()
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let (_, key) = key.into_parts();
+                let mut labels: Vec<(String, String)> = key
+                    .labels()
+                    .map(|label| (label.key().to_string(), label.value().to_string()))
+                    .collect();
+                labels.sort();
+                ((key.name().to_string(), labels), value)
+            })
+            .collect()
+    }
+
+    fn get_labels(labels: &[(&str, &str)]) -> Vec<(String, String)> {
+        labels
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_validation_metrics() {
+        let recorder = DebuggingRecorder::new();
+        let clock = Arc::new(FakeClock::new(1717979639));
+        let mut issuer = MockIssuer::new(TEAM_NAME).with_clock(clock.clone());
+        let validator =
+            TeamValidator::from_team_keys(issuer.to_team_keys()).with_clock(clock.clone());
+
+        let mut constraints = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
+        constraints.set_audience(&[AUDIENCE]);
+
+        ::metrics::with_local_recorder(&recorder, || {
+            let token = issuer.app_token(AUDIENCE).sign();
+            assert!(validator
+                .validate_token(&token, TEAM_NAME, &mut constraints)
+                .is_ok());
+            assert!(validator
+                .validate_token(&token, TEAM_NAME, &mut constraints)
+                .is_ok());
+            assert!(validator
+                .validate_token("garbage", TEAM_NAME, &mut constraints)
+                .is_err());
+
+            issuer.rotate();
+            clock.advance(Duration::from_secs(30));
+            let team_keys = TeamKeys::from_json(TEAM_NAME, issuer.to_certs_json()).unwrap();
+            assert!(validator.update_keys(team_keys));
+            let token = issuer.app_token(AUDIENCE).sign();
+            assert!(validator
+                .validate_token(&token, TEAM_NAME, &mut constraints)
+                .is_ok());
+            let token = issuer.app_token(AUDIENCE).with_key_id("unknown").sign();
+            assert!(validator
+                .validate_token(&token, TEAM_NAME, &mut constraints)
+                .is_err());
+        });
+
+        let snapshot = take_snapshot(&recorder);
+        let get_counter = |name: &str, labels: &[(&str, &str)]| match snapshot
+            .get(&(name.to_string(), get_labels(labels)))
+        {
+            Some(DebugValue::Counter(count)) => *count,
+            _ => 0,
+        };
+
+        let ok_labels = [
+            ("audience", AUDIENCE),
+            ("outcome", "ok"),
+            ("team", TEAM_NAME),
+        ];
+        assert_eq!(get_counter(VALIDATIONS_TOTAL, &ok_labels), 3);
+        let failed_labels = [
+            ("audience", AUDIENCE),
+            ("outcome", "header_decode_failure"),
+            ("team", TEAM_NAME),
+        ];
+        assert_eq!(get_counter(VALIDATIONS_TOTAL, &failed_labels), 1);
+        let unknown_labels = [
+            ("audience", AUDIENCE),
+            ("outcome", "no_kid_in_cache"),
+            ("team", TEAM_NAME),
+        ];
+        assert_eq!(get_counter(VALIDATIONS_TOTAL, &unknown_labels), 1);
+
+        // keys are prewarmed, so only the hits are counted for known kids
+        assert_eq!(
+            get_counter(DECODING_KEY_LOOKUPS_TOTAL, &[("result", "hit")]),
+            3
+        );
+        assert_eq!(
+            get_counter(DECODING_KEY_LOOKUPS_TOTAL, &[("result", "unknown")]),
+            1
+        );
+        assert_eq!(get_counter(KEY_ROTATIONS_TOTAL, &[("team", TEAM_NAME)]), 1);
+
+        let durations = snapshot.get(&(
+            VALIDATION_DURATION_SECONDS.to_string(),
+            get_labels(&[("outcome", "ok"), ("team", TEAM_NAME)]),
+        ));
+        assert!(matches!(durations, Some(DebugValue::Histogram(values)) if values.len() == 3));
+
+        let age = snapshot.get(&(
+            KEY_SET_AGE_SECONDS.to_string(),
+            get_labels(&[("team", TEAM_NAME)]),
+        ));
+        assert!(matches!(age, Some(DebugValue::Gauge(age)) if age.into_inner() == 0.0));
+    }
+
+    #[test]
+    fn test_sync_metrics() {
+        let recorder = DebuggingRecorder::new();
+        let issuer = MockIssuer::new(TEAM_NAME);
+        let validator = TeamValidator::from_team_keys(issuer.to_team_keys())
+            .with_certs_url("http://127.0.0.1:1/cdn-cgi/access/certs");
+
+        ::metrics::with_local_recorder(&recorder, || {
+            assert!(validator.sync().is_err());
+        });
+
+        let snapshot = take_snapshot(&recorder);
+        let key = (
+            SYNCS_TOTAL.to_string(),
+            get_labels(&[("outcome", "failure"), ("team", TEAM_NAME)]),
+        );
+        assert!(matches!(snapshot.get(&key), Some(DebugValue::Counter(1))));
+    }
+}
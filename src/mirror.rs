@@ -0,0 +1,230 @@
+use crate::{
+    api::{ParseMode, TeamKeys},
+    StdResult,
+};
+
+use std::{collections::HashMap, sync::RwLock};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Request, Response, Server};
+
+const CERTS_PATH: &str = "/cdn-cgi/access/certs";
+const TEAM_NAME_PLACEHOLDER: &str = "{team_name}";
+
+fn strip_query(url: &str) -> &str {
+    url.split_once('?').map_or(url, |(path, _)| path)
+}
+
+fn get_host_team_name(host: &str) -> Option<&str> {
+    host.split_once('.').map(|(team_name, _)| team_name)
+}
+
+fn get_header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Mirrors the `/cdn-cgi/access/certs` endpoints of several teams, so that validators
+/// without access to the CF API can load and sync from it using `TeamValidator::from_certs_url`.
+///
+/// Keys are served at `/<team_name>/cdn-cgi/access/certs`, or at `/cdn-cgi/access/certs`
+/// when the first label of the Host header is the team name.
+pub struct KeyMirror {
+    team_names: Vec<String>,
+    upstream: Option<String>,
+    parse_mode: ParseMode,
+    payloads: RwLock<HashMap<String, Value>>,
+}
+
+impl KeyMirror {
+    /// Constructs a KeyMirror for a set of teams. Nothing is served until a team is synced.
+    pub fn new(team_names: &[&str]) -> Self {
+        KeyMirror {
+            team_names: team_names.iter().map(|name| name.to_string()).collect(),
+            upstream: None,
+            parse_mode: ParseMode::Strict,
+            payloads: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Syncs from another certs endpoint instead of the CF API, e.g. an upstream mirror.
+    /// Occurrences of `{team_name}` in the URL are replaced with the team name.
+    pub fn with_upstream(mut self, url: &str) -> Self {
+        self.upstream = Some(url.to_string());
+        self
+    }
+
+    /// Sets the ParseMode used when syncing keys.
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// Returns the names of the mirrored teams.
+    pub fn get_team_names(&self) -> Vec<String> {
+        self.team_names.clone()
+    }
+
+    /// Replaces the keys served for a team.
+    pub fn update_team(&self, team_keys: &TeamKeys) {
+        self.payloads
+            .write()
+            .unwrap()
+            .insert(team_keys.team_name.clone(), team_keys.to_certs_json());
+    }
+
+    /// Attempts to sync the keys of a single team. On failure the team
+    /// continues to be served with the last keys that were synced.
+    pub fn sync_team(&self, team_name: &str) -> StdResult<()> {
+        let team_keys = match &self.upstream {
+            Some(url) => TeamKeys::from_url_with_mode(
+                team_name,
+                &url.replace(TEAM_NAME_PLACEHOLDER, team_name),
+                self.parse_mode,
+            )?,
+            None => TeamKeys::from_team_name_with_mode(team_name, self.parse_mode)?,
+        };
+
+        self.update_team(&team_keys);
+        Ok(())
+    }
+
+    /// Returns the certs payload currently served for a team.
+    pub fn get_payload(&self, team_name: &str) -> Option<Value> {
+        self.payloads.read().unwrap().get(team_name).cloned()
+    }
+
+    fn get_certs(&self, team_name: &str) -> (u16, Value) {
+        if !self.team_names.iter().any(|name| name == team_name) {
+            return (
+                404,
+                json!({"error": format!("team '{team_name}' is not mirrored")}),
+            );
+        }
+
+        match self.get_payload(team_name) {
+            Some(payload) => (200, payload),
+            None => (
+                503,
+                json!({"error": format!("team '{team_name}' has not been synced")}),
+            ),
+        }
+    }
+
+    fn get_health(&self) -> (u16, Value) {
+        let payloads = self.payloads.read().unwrap();
+        let teams: HashMap<&String, bool> = self
+            .team_names
+            .iter()
+            .map(|name| (name, payloads.contains_key(name)))
+            .collect();
+
+        (200, json!({"status": "ok", "teams": teams}))
+    }
+
+    /// Resolves a request to a status code and JSON body.
+    pub fn handle(&self, host: Option<&str>, url: &str) -> (u16, Value) {
+        let path = strip_query(url);
+
+        if path == "/healthz" {
+            return self.get_health();
+        }
+
+        if path == CERTS_PATH {
+            if let Some(team_name) = host.and_then(get_host_team_name) {
+                return self.get_certs(team_name);
+            }
+        }
+
+        match path
+            .strip_prefix('/')
+            .and_then(|path| path.strip_suffix(CERTS_PATH))
+        {
+            Some(team_name) if !team_name.is_empty() && !team_name.contains('/') => {
+                self.get_certs(team_name)
+            }
+            _ => (404, json!({"error": "not found"})),
+        }
+    }
+
+    /// Serves requests from a tiny_http Server until it is unblocked.
+    pub fn serve(&self, server: &Server) {
+        let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+
+        for request in server.incoming_requests() {
+            let (status, body) = self.handle(get_header(&request, "Host"), request.url());
+            let response = Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header(content_type.clone());
+            let _ = request.respond(response);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TeamValidator, Validator};
+    use std::{sync::Arc, thread};
+
+    const TEAM_NAME: &str = "molten";
+    const STATIC_KEYS: &str = include_str!("../test_data/sample_signing_keys.json");
+
+    fn get_mirror() -> KeyMirror {
+        let mirror = KeyMirror::new(&[TEAM_NAME, "unsynced"]);
+        mirror.update_team(&TeamKeys::from_str(TEAM_NAME, STATIC_KEYS).unwrap());
+        mirror
+    }
+
+    #[test]
+    fn test_handle() {
+        let mirror = get_mirror();
+
+        let (status, body) = mirror.handle(None, "/molten/cdn-cgi/access/certs");
+        assert_eq!(status, 200);
+        assert_eq!(body, mirror.get_payload(TEAM_NAME).unwrap());
+
+        let (status, _) = mirror.handle(Some("molten.keys.internal:8080"), "/cdn-cgi/access/certs");
+        assert_eq!(status, 200);
+
+        let (status, _) = mirror.handle(None, "/unsynced/cdn-cgi/access/certs");
+        assert_eq!(status, 503);
+
+        let (status, _) = mirror.handle(None, "/other/cdn-cgi/access/certs");
+        assert_eq!(status, 404);
+
+        let (status, body) = mirror.handle(None, "/healthz");
+        assert_eq!(status, 200);
+        assert_eq!(body["teams"][TEAM_NAME], true);
+        assert_eq!(body["teams"]["unsynced"], false);
+    }
+
+    #[test]
+    fn test_serve() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let mirror = Arc::new(get_mirror());
+
+        let serving = mirror.clone();
+        thread::spawn(move || serving.serve(&server));
+
+        let url = format!("http://{addr}/{TEAM_NAME}/cdn-cgi/access/certs");
+        let team_keys = TeamKeys::from_url(TEAM_NAME, &url).unwrap();
+        assert_eq!(team_keys.keys.len(), 2);
+
+        let validator = TeamValidator::from_certs_url(TEAM_NAME, &url).unwrap();
+        assert!(!validator.sync().unwrap());
+
+        // mirrors can be chained
+        let downstream = KeyMirror::new(&[TEAM_NAME])
+            .with_upstream(&format!("http://{addr}/{{team_name}}/cdn-cgi/access/certs"));
+        downstream.sync_team(TEAM_NAME).unwrap();
+        assert_eq!(
+            downstream.get_payload(TEAM_NAME),
+            mirror.get_payload(TEAM_NAME)
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use crate::testing::MockIssuer;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+
+const CERTS_PATH: &str = "/cdn-cgi/access/certs";
+
+/// A scripted response of the MockCertsServer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockResponse {
+    /// Responds with a certs payload.
+    Keys(Value),
+    /// Responds with an error status code.
+    Error(u16),
+    /// Responds with a raw body, e.g. a malformed payload.
+    Body(String),
+}
+
+#[derive(Default)]
+struct TeamState {
+    payload: Option<Value>,
+    scripted: VecDeque<MockResponse>,
+    requests: usize,
+}
+
+#[derive(Default)]
+struct State {
+    teams: HashMap<String, TeamState>,
+    latency: Duration,
+}
+
+impl State {
+    fn respond(&mut self, team_name: &str) -> MockResponse {
+        let team = match self.teams.get_mut(team_name) {
+            Some(team) => team,
+            None => return MockResponse::Error(404),
+        };
+        team.requests += 1;
+
+        if let Some(response) = team.scripted.pop_front() {
+            return response;
+        }
+
+        match &team.payload {
+            Some(payload) => MockResponse::Keys(payload.clone()),
+            None => MockResponse::Error(404),
+        }
+    }
+}
+
+fn get_team_name(url: &str) -> Option<&str> {
+    let path = url.split_once('?').map_or(url, |(path, _)| path);
+
+    path.strip_prefix('/')?
+        .strip_suffix(CERTS_PATH)
+        .filter(|team_name| !team_name.is_empty() && !team_name.contains('/'))
+}
+
+/// Serves `/<team_name>/cdn-cgi/access/certs` on localhost for tests, standing in for the CF API.
+/// Point validators at it with `TeamValidator::with_certs_url` or `TeamKeys::from_url`.
+///
+/// Teams serve their current keys unless a scripted response is queued,
+/// so that rotations, errors and malformed payloads can be simulated.
+pub struct MockCertsServer {
+    server: Arc<Server>,
+    state: Arc<Mutex<State>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockCertsServer {
+    /// Starts a MockCertsServer on an ephemeral port, serving no teams.
+    pub fn start() -> Self {
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        let state: Arc<Mutex<State>> = Arc::default();
+
+        let serving = server.clone();
+        let serving_state = state.clone();
+        let handle = thread::spawn(move || {
+            let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+
+            for request in serving.incoming_requests() {
+                let (response, latency) = {
+                    let mut state = serving_state.lock().unwrap();
+                    let response = match get_team_name(request.url()) {
+                        Some(team_name) => state.respond(team_name),
+                        None => MockResponse::Error(404),
+                    };
+                    (response, state.latency)
+                };
+
+                thread::sleep(latency);
+
+                let (status, body) = match response {
+                    MockResponse::Keys(payload) => (200, payload.to_string()),
+                    MockResponse::Error(status) => {
+                        (status, json!({"error": "mock error"}).to_string())
+                    }
+                    MockResponse::Body(body) => (200, body),
+                };
+
+                let response = Response::from_string(body)
+                    .with_status_code(status)
+                    .with_header(content_type.clone());
+                let _ = request.respond(response);
+            }
+        });
+
+        MockCertsServer {
+            server,
+            state,
+            handle: Some(handle),
+        }
+    }
+
+    /// Returns the certs URL of a team.
+    pub fn get_certs_url(&self, team_name: &str) -> String {
+        format!(
+            "http://{}/{team_name}{CERTS_PATH}",
+            self.server.server_addr()
+        )
+    }
+
+    /// Returns the certs URL with a `{team_name}` placeholder, e.g. for `KeyMirror::with_upstream`.
+    pub fn get_url_template(&self) -> String {
+        self.get_certs_url("{team_name}")
+    }
+
+    /// Replaces the keys served for a team.
+    pub fn set_keys(&self, team_name: &str, payload: Value) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .teams
+            .entry(team_name.to_string())
+            .or_default()
+            .payload = Some(payload);
+    }
+
+    /// Serves the keys currently published by a MockIssuer.
+    /// Call again after rotating the issuer's keys.
+    pub fn publish(&self, issuer: &MockIssuer) {
+        self.set_keys(&issuer.get_team_name(), issuer.to_certs_json());
+    }
+
+    /// Queues a response to be served once for a team before its keys are served again.
+    pub fn push_response(&self, team_name: &str, response: MockResponse) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .teams
+            .entry(team_name.to_string())
+            .or_default()
+            .scripted
+            .push_back(response);
+    }
+
+    /// Delays every response by the given duration.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Returns the number of requests received for a team.
+    pub fn get_request_count(&self, team_name: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        state.teams.get(team_name).map_or(0, |team| team.requests)
+    }
+}
+
+impl Drop for MockCertsServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::TeamKeys, TeamValidator, Validator};
+    use std::time::Instant;
+
+    const TEAM_NAME: &str = "molten";
+
+    #[test]
+    fn test_scripted_responses() {
+        let server = MockCertsServer::start();
+        let mut issuer = MockIssuer::new(TEAM_NAME);
+        server.publish(&issuer);
+        let url = server.get_certs_url(TEAM_NAME);
+
+        let team_keys = TeamKeys::from_url(TEAM_NAME, &url).unwrap();
+        assert_eq!(team_keys.latest_key_id, issuer.get_latest_key_id());
+
+        server.push_response(TEAM_NAME, MockResponse::Error(500));
+        server.push_response(TEAM_NAME, MockResponse::Body("{\"keys\": [".to_string()));
+        assert!(TeamKeys::from_url(TEAM_NAME, &url).is_err());
+        assert!(TeamKeys::from_url(TEAM_NAME, &url).is_err());
+        assert!(TeamKeys::from_url(TEAM_NAME, &url).is_ok());
+        assert_eq!(server.get_request_count(TEAM_NAME), 4);
+
+        assert!(TeamKeys::from_url("other", &server.get_certs_url("other")).is_err());
+
+        // rotations are picked up by validators on their next sync
+        let validator = TeamValidator::from_team_keys(team_keys).with_certs_url(&url);
+        let previous_key_id = issuer.get_latest_key_id();
+        issuer.rotate();
+        issuer.retire(&previous_key_id);
+        server.publish(&issuer);
+        assert!(validator.sync().unwrap());
+        assert!(!validator.sync().unwrap());
+    }
+
+    #[test]
+    fn test_latency() {
+        let server = MockCertsServer::start();
+        server.publish(&MockIssuer::new(TEAM_NAME));
+        server.set_latency(Duration::from_millis(200));
+
+        let started = Instant::now();
+        assert!(TeamKeys::from_url(TEAM_NAME, &server.get_certs_url(TEAM_NAME)).is_ok());
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
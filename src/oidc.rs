@@ -0,0 +1,595 @@
+use crate::{
+    api,
+    cache::Cache,
+    clock::{Clock, SystemClock},
+    errors::{UnpackError, ValidationError, ValidationResult},
+    unpack, DecodedToken, KeyPolicy, StdResult, Validator,
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
+
+use jsonwebtoken::{Algorithm, Validation};
+use serde_json::Value;
+
+fn get_team_origin(team_name: &str) -> String {
+    format!("https://{team_name}.cloudflareaccess.com")
+}
+
+fn get_issuer(team_name: &str, client_id: &str) -> String {
+    format!(
+        "{}/cdn-cgi/access/sso/oidc/{client_id}",
+        get_team_origin(team_name)
+    )
+}
+
+fn get_discovery_uri(team_name: &str, client_id: &str) -> String {
+    format!(
+        "{}/.well-known/openid-configuration",
+        get_issuer(team_name, client_id)
+    )
+}
+
+fn get_jwks(team_name: &str, jwks_uri: &str) -> StdResult<api::TeamKeys> {
+    let payload = api::get_json_payload(jwks_uri)?;
+    parse_jwks(team_name, &payload)
+}
+
+fn get_audience_count(token_data: &DecodedToken) -> usize {
+    match token_data.claims.get("aud") {
+        Some(Value::Array(audiences)) => audiences.len(),
+        Some(Value::String(_)) => 1,
+        _ => 0,
+    }
+}
+
+// A JWKS has no notion of a latest key, so the first key is treated as the latest one.
+// It is only prewarmed in the Cache, but like the latest key of the CF API it cannot be
+// dropped by a KeyPolicy or key ID verification: if it fails either, the whole JWKS is rejected.
+fn parse_jwks(team_name: &str, payload: &Value) -> StdResult<api::TeamKeys> {
+    Ok(api::TeamKeys::new(
+        team_name,
+        &api::extract_first_key_id(payload)?,
+        api::extract_current_keys(payload)?,
+        Vec::new(),
+        HashMap::new(),
+    ))
+}
+
+// id_tokens are verified with keys from the JWKS, so symmetric algorithms never apply
+fn is_asymmetric(alg: &Algorithm) -> bool {
+    !matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+/// The subset of an OIDC discovery document needed to validate id_tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcDiscovery {
+    pub issuer: String,
+    pub jwks_uri: String,
+    /// The asymmetric algorithms listed in `id_token_signing_alg_values_supported`.
+    pub signing_algorithms: Vec<Algorithm>,
+}
+
+impl OidcDiscovery {
+    /// Attempts to fetch the discovery document of a Cloudflare Access for SaaS OIDC application.
+    pub fn from_team_name(team_name: &str, client_id: &str) -> StdResult<Self> {
+        let payload = api::get_json_payload(&get_discovery_uri(team_name, client_id))?;
+        OidcDiscovery::from_json(team_name, client_id, &payload)
+    }
+
+    /// Attempts to load the discovery document of an OIDC application
+    /// from a given serde_json::Value struct, see `check`.
+    pub fn from_json(team_name: &str, client_id: &str, json_val: &Value) -> StdResult<Self> {
+        let get_str_field = |key: &str| -> StdResult<String> {
+            Ok(unpack::as_string(unpack::as_object_get_key(json_val, key)?)?.clone())
+        };
+
+        // unknown algorithms, such as "none", are never accepted
+        let signing_algorithms = unpack::as_array(unpack::as_object_get_key(
+            json_val,
+            "id_token_signing_alg_values_supported",
+        )?)?
+        .iter()
+        .filter_map(|alg| Algorithm::from_str(alg.as_str()?).ok())
+        .filter(is_asymmetric)
+        .collect();
+
+        let discovery = OidcDiscovery {
+            issuer: get_str_field("issuer")?,
+            jwks_uri: get_str_field("jwks_uri")?,
+            signing_algorithms,
+        };
+        discovery.check(team_name, client_id)?;
+
+        Ok(discovery)
+    }
+
+    /// Checks that the document belongs to the OIDC application: the issuer must be
+    /// the one the document was retrieved from (OIDC Discovery section 4.3),
+    /// the JWKS must be served by the team domain and an asymmetric signing algorithm must be listed.
+    pub fn check(&self, team_name: &str, client_id: &str) -> StdResult<()> {
+        let issuer = get_issuer(team_name, client_id);
+        if self.issuer != issuer {
+            return Err(UnpackError::unexpected_value("issuer", &self.issuer, &issuer).into());
+        }
+
+        let origin = format!("{}/", get_team_origin(team_name));
+        if !self.jwks_uri.starts_with(&origin) {
+            let expect = format!("a url under {origin}");
+            return Err(UnpackError::unexpected_value("jwks_uri", &self.jwks_uri, &expect).into());
+        }
+
+        if !self.signing_algorithms.iter().any(is_asymmetric) {
+            return Err(
+                UnpackError::empty_container("id_token_signing_alg_values_supported").into(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Validates id_tokens issued by Cloudflare Access acting as an OIDC provider
+/// for a single SaaS application.
+///
+/// Signing keys are fetched from the JWKS advertised by the discovery document
+/// and held in a Cache, so they rotate the same way as Application Token keys.
+pub struct OidcClientValidator {
+    team_name: String,
+    client_id: String,
+    discovery: OidcDiscovery,
+    cache: Cache,
+    clock: Arc<dyn Clock>,
+    key_policy: Option<KeyPolicy>,
+    verify_key_ids: bool,
+}
+
+impl OidcClientValidator {
+    /// Initialises an OidcClientValidator from a discovery document and a JWKS payload.
+    /// Fails if the discovery document does not belong to the application.
+    pub fn new(
+        team_name: &str,
+        client_id: &str,
+        discovery: OidcDiscovery,
+        jwks: &Value,
+    ) -> StdResult<Self> {
+        discovery.check(team_name, client_id)?;
+        let team_keys = parse_jwks(team_name, jwks)?;
+
+        Ok(OidcClientValidator {
+            team_name: team_name.to_string(),
+            client_id: client_id.to_string(),
+            discovery,
+            cache: Cache::new(&team_keys.latest_key_id, team_keys.keys),
+            clock: Arc::new(SystemClock),
+            key_policy: None,
+            verify_key_ids: false,
+        })
+    }
+
+    /// Applies a KeyPolicy to the current keys, to keys synced from the JWKS
+    /// and to every key used to validate an id_token.
+    /// Fails if the first key of the JWKS violates the policy.
+    pub fn with_key_policy(mut self, policy: KeyPolicy) -> StdResult<Self> {
+        self.key_policy = Some(policy);
+        self.recheck_current_keys()?;
+        Ok(self)
+    }
+
+    /// Requires the kid of every RSA key to be one of its thumbprints, both for the current keys
+    /// and for keys synced from the JWKS. Fails if a current key does not pass verification.
+    pub fn with_key_id_verification(mut self) -> StdResult<Self> {
+        self.verify_key_ids = true;
+        self.recheck_current_keys()?;
+        Ok(self)
+    }
+
+    /// Reads the current time from the given Clock instead of the system time, for `exp`/`nbf` checks.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Attempts to initialise an OidcClientValidator using a team name and client ID.
+    /// The discovery document and keys are retrieved from the CF API.
+    pub fn from_team_name(team_name: &str, client_id: &str) -> StdResult<Self> {
+        let discovery = OidcDiscovery::from_team_name(team_name, client_id)?;
+        let jwks = api::get_json_payload(&discovery.jwks_uri)?;
+        OidcClientValidator::new(team_name, client_id, discovery, &jwks)
+    }
+
+    /// Returns the issuer expected in the `iss` claim of id_tokens.
+    pub fn get_issuer(&self) -> &str {
+        &self.discovery.issuer
+    }
+
+    fn check_keys(&self, team_keys: &mut api::TeamKeys) -> StdResult<()> {
+        if self.verify_key_ids {
+            team_keys.verify_key_ids(api::ParseMode::Strict)?;
+        }
+
+        if let Some(policy) = &self.key_policy {
+            team_keys.apply_key_policy(policy)?;
+        }
+
+        Ok(())
+    }
+
+    fn recheck_current_keys(&self) -> StdResult<()> {
+        let (latest_key_id, jwks) = self.cache.get_jwks();
+        let mut team_keys = api::TeamKeys::from_json(
+            &self.team_name,
+            serde_json::json!({"keys": jwks, "public_cert": {"kid": latest_key_id}}),
+        )?;
+        let key_count = team_keys.keys.len();
+        self.check_keys(&mut team_keys)?;
+
+        if team_keys.keys.len() != key_count {
+            self.cache
+                .rotate_keys(&team_keys.latest_key_id, team_keys.keys);
+        }
+
+        Ok(())
+    }
+
+    fn check_azp(&self, token_data: &DecodedToken) -> ValidationResult<()> {
+        let azp = token_data.claims.get("azp").and_then(|azp| azp.as_str());
+
+        match azp {
+            Some(azp) if azp != self.client_id => {
+                Err(ValidationError::azp_mismatch(&self.client_id))
+            }
+            // azp is required when the token has more than one audience
+            None if get_audience_count(token_data) > 1 => {
+                Err(ValidationError::azp_mismatch(&self.client_id))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_nonce(&self, token_data: &DecodedToken, nonce: &str) -> ValidationResult<()> {
+        match token_data.claims.get("nonce").and_then(|val| val.as_str()) {
+            Some(actual) if actual == nonce => Ok(()),
+            _ => Err(ValidationError::nonce_mismatch()),
+        }
+    }
+
+    /// Attempts to validate an id_token, checking the signature, issuer, audience and `azp`.
+    /// The token must be signed with one of the algorithms listed by the discovery document.
+    /// If a nonce was sent in the authentication request, the `nonce` claim must match it.
+    pub fn validate_id_token(
+        &self,
+        token: &str,
+        nonce: Option<&str>,
+    ) -> ValidationResult<DecodedToken> {
+        // jsonwebtoken rejects algorithms of a family other than the key's, so only the
+        // advertised algorithm the token claims is allowed
+        let header = crate::decode_token_header(token)?;
+        if !self.discovery.signing_algorithms.contains(&header.alg) {
+            return Err(ValidationError::invalid_jwt());
+        }
+
+        let mut constraints = Validation::new(header.alg);
+        let token_data = self.validate_token(token, &self.team_name, &mut constraints)?;
+
+        if let Some(nonce) = nonce {
+            self.check_nonce(&token_data, nonce)?;
+        }
+
+        Ok(token_data)
+    }
+}
+
+impl Validator for OidcClientValidator {
+    /// Attempts to validate an id_token using the provided constraints.
+    /// The issuer and audience are always pinned to the OIDC application,
+    /// replacing any `iss` or `aud` constraints that allow the application's values
+    /// and failing on constraints that exclude them.
+    fn validate_token(
+        &self,
+        token: &str,
+        team_name: &str,
+        constraints: &mut Validation,
+    ) -> ValidationResult<DecodedToken> {
+        if team_name != self.team_name {
+            return Err(ValidationError::team_name_mismatch(
+                team_name,
+                self.team_name.as_str(),
+            ));
+        }
+
+        if let Some(issuers) = &constraints.iss {
+            if !issuers.contains(&self.discovery.issuer) {
+                return Err(ValidationError::constraint_conflict(
+                    "iss",
+                    &self.discovery.issuer,
+                ));
+            }
+        }
+
+        if let Some(audiences) = &constraints.aud {
+            if !audiences.contains(&self.client_id) {
+                return Err(ValidationError::constraint_conflict("aud", &self.client_id));
+            }
+        }
+
+        constraints.set_issuer(&[&self.discovery.issuer]);
+        constraints.set_audience(&[&self.client_id]);
+
+        let header = crate::decode_token_header(token)?;
+        let key_id = crate::get_kid(header.clone())?;
+
+        if let (Some(policy), Some(jwk)) = (&self.key_policy, self.cache.get_jwk(&key_id)) {
+            policy.check_token_header(&header, &jwk)?;
+        }
+
+        let key = self.cache.get_decoding_key(&key_id)?;
+        let token_data = crate::decode_token(token, &key, constraints, self.clock.timestamp())?;
+        self.check_azp(&token_data)?;
+        Ok(token_data)
+    }
+
+    /// Attempts to syncronise the cached keys with the JWKS advertised
+    /// by the discovery document. Returns a wrapped bool signalling
+    /// if an update was necessary.
+    fn sync(&self) -> StdResult<bool> {
+        let mut team_keys = get_jwks(&self.team_name, &self.discovery.jwks_uri)?;
+        self.check_keys(&mut team_keys)?;
+
+        let key_ids: HashSet<String> = team_keys.keys.keys().cloned().collect();
+        let rotate = self.cache.is_rotation_needed(key_ids);
+
+        if rotate {
+            self.cache
+                .rotate_keys(&team_keys.latest_key_id, team_keys.keys);
+        }
+
+        Ok(rotate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::FakeClock,
+        testing::{MockIssuer, MockToken},
+    };
+    use serde_json::json;
+
+    const TEAM_NAME: &str = "molten";
+    const CLIENT_ID: &str = "mock-client";
+    const NONCE: &str = "n-0S6_WzA2Mj";
+    const ISSUED_AT: u64 = 1717979639;
+    const DISCOVERY: &str = include_str!("../test_data/mock_oidc_configuration.json");
+
+    fn get_discovery_json() -> Value {
+        serde_json::from_str(DISCOVERY).unwrap()
+    }
+
+    fn get_jwks(issuer: &MockIssuer) -> Value {
+        json!({"keys": issuer.to_certs_json()["keys"]})
+    }
+
+    fn get_validator(issuer: &MockIssuer, discovery_json: &Value) -> OidcClientValidator {
+        let discovery = OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, discovery_json).unwrap();
+        OidcClientValidator::new(TEAM_NAME, CLIENT_ID, discovery, &get_jwks(issuer))
+            .unwrap()
+            .with_clock(Arc::new(FakeClock::new(ISSUED_AT)))
+    }
+
+    fn get_fixture() -> (MockIssuer, OidcClientValidator) {
+        let issuer = MockIssuer::new(TEAM_NAME).with_clock(Arc::new(FakeClock::new(ISSUED_AT)));
+        let validator = get_validator(&issuer, &get_discovery_json());
+        (issuer, validator)
+    }
+
+    fn id_token(issuer: &MockIssuer) -> MockToken<'_> {
+        issuer
+            .app_token(CLIENT_ID)
+            .with_claim("iss", json!(get_issuer(TEAM_NAME, CLIENT_ID)))
+            .with_claim("aud", json!(CLIENT_ID))
+            .with_claim("nonce", json!(NONCE))
+    }
+
+    #[test]
+    fn test_discovery_from_json() {
+        let discovery =
+            OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, &get_discovery_json()).unwrap();
+
+        assert_eq!(
+            discovery.jwks_uri,
+            "https://molten.cloudflareaccess.com/cdn-cgi/access/sso/oidc/mock-client/jwks"
+        );
+        assert_eq!(discovery.signing_algorithms, vec![Algorithm::RS256]);
+        assert_eq!(get_discovery_uri(TEAM_NAME, CLIENT_ID), "https://molten.cloudflareaccess.com/cdn-cgi/access/sso/oidc/mock-client/.well-known/openid-configuration");
+    }
+
+    #[test]
+    fn test_discovery_mismatch() {
+        let json_val = get_discovery_json();
+        assert!(OidcDiscovery::from_json("other", CLIENT_ID, &json_val).is_err());
+        assert!(OidcDiscovery::from_json(TEAM_NAME, "other-client", &json_val).is_err());
+
+        let mut json_val = get_discovery_json();
+        json_val["issuer"] = json!("https://attacker.example/cdn-cgi/access/sso/oidc/mock-client");
+        assert!(OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, &json_val).is_err());
+
+        for jwks_uri in [
+            "https://attacker.example/jwks",
+            "https://molten.cloudflareaccess.com.attacker.example/jwks",
+            "http://molten.cloudflareaccess.com/cdn-cgi/access/sso/oidc/mock-client/jwks",
+        ] {
+            let mut json_val = get_discovery_json();
+            json_val["jwks_uri"] = json!(jwks_uri);
+            assert!(OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, &json_val).is_err());
+        }
+
+        // discovery documents constructed directly are checked too
+        let discovery = OidcDiscovery {
+            issuer: "https://attacker.example".to_string(),
+            jwks_uri: "https://attacker.example/jwks".to_string(),
+            signing_algorithms: vec![Algorithm::RS256],
+        };
+        let jwks = get_jwks(&MockIssuer::new(TEAM_NAME));
+        assert!(OidcClientValidator::new(TEAM_NAME, CLIENT_ID, discovery, &jwks).is_err());
+    }
+
+    #[test]
+    fn test_discovery_signing_algorithms() {
+        let mut json_val = get_discovery_json();
+        json_val["id_token_signing_alg_values_supported"] = json!(["none", "HS256", "ES256"]);
+        let discovery = OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, &json_val).unwrap();
+        assert_eq!(discovery.signing_algorithms, vec![Algorithm::ES256]);
+
+        for algorithms in [json!(["none", "HS256"]), json!([]), json!("RS256")] {
+            let mut json_val = get_discovery_json();
+            json_val["id_token_signing_alg_values_supported"] = algorithms;
+            assert!(OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, &json_val).is_err());
+        }
+
+        let mut json_val = get_discovery_json();
+        json_val
+            .as_object_mut()
+            .unwrap()
+            .remove("id_token_signing_alg_values_supported");
+        assert!(OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, &json_val).is_err());
+    }
+
+    #[test]
+    fn test_validate_id_token() {
+        let (issuer, validator) = get_fixture();
+        let token = id_token(&issuer).sign();
+
+        let result = validator.validate_id_token(&token, Some(NONCE));
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().claims["email"], "user@example.com");
+
+        assert!(validator.validate_id_token(&token, None).is_ok());
+        assert!(validator
+            .validate_id_token(&token, Some("replayed"))
+            .is_err());
+
+        let token = id_token(&issuer).with_expiry(ISSUED_AT - 3600).sign();
+        let result = validator.validate_id_token(&token, None);
+        assert_eq!(result.unwrap_err().get_kind(), "token_expired");
+    }
+
+    #[test]
+    fn test_validate_id_token_algorithm() {
+        let issuer = MockIssuer::new(TEAM_NAME).with_clock(Arc::new(FakeClock::new(ISSUED_AT)));
+        let token = id_token(&issuer).sign();
+
+        // MockIssuer signs with RS256, which the application no longer advertises
+        let mut json_val = get_discovery_json();
+        json_val["id_token_signing_alg_values_supported"] = json!(["ES256"]);
+        let validator = get_validator(&issuer, &json_val);
+        assert!(validator.validate_id_token(&token, None).is_err());
+
+        let mut json_val = get_discovery_json();
+        json_val["id_token_signing_alg_values_supported"] = json!(["ES256", "RS256"]);
+        let validator = get_validator(&issuer, &json_val);
+        assert!(validator.validate_id_token(&token, None).is_ok());
+    }
+
+    #[test]
+    fn test_validate_id_token_issuer_and_audience() {
+        let (issuer, validator) = get_fixture();
+
+        let token = id_token(&issuer)
+            .with_claim("iss", json!("https://molten.cloudflareaccess.com"))
+            .sign();
+        assert!(validator.validate_id_token(&token, None).is_err());
+
+        let token = id_token(&issuer)
+            .with_claim("aud", json!("other-client"))
+            .sign();
+        assert!(validator.validate_id_token(&token, None).is_err());
+    }
+
+    #[test]
+    fn test_validate_token_constraints() {
+        let (issuer, validator) = get_fixture();
+        let token = id_token(&issuer).sign();
+
+        let mut constraints = Validation::new(Algorithm::RS256);
+        constraints.set_audience(&[CLIENT_ID, "other-client"]);
+        assert!(validator
+            .validate_token(&token, TEAM_NAME, &mut constraints)
+            .is_ok());
+
+        let mut constraints = Validation::new(Algorithm::RS256);
+        constraints.set_audience(&["other-client"]);
+        let result = validator.validate_token(&token, TEAM_NAME, &mut constraints);
+        assert_eq!(result.unwrap_err().get_kind(), "constraint_conflict");
+
+        let mut constraints = Validation::new(Algorithm::RS256);
+        constraints.set_issuer(&["https://molten.cloudflareaccess.com"]);
+        let result = validator.validate_token(&token, TEAM_NAME, &mut constraints);
+        assert_eq!(result.unwrap_err().get_kind(), "constraint_conflict");
+    }
+
+    #[test]
+    fn test_validate_id_token_azp() {
+        let (issuer, validator) = get_fixture();
+
+        let token = id_token(&issuer)
+            .with_claim("aud", json!([CLIENT_ID, "other-client"]))
+            .with_claim("azp", json!(CLIENT_ID))
+            .sign();
+        assert!(validator.validate_id_token(&token, None).is_ok());
+
+        let token = id_token(&issuer)
+            .with_claim("aud", json!([CLIENT_ID, "other-client"]))
+            .sign();
+        assert!(validator.validate_id_token(&token, None).is_err());
+
+        let token = id_token(&issuer)
+            .with_claim("azp", json!("other-client"))
+            .sign();
+        assert!(validator.validate_id_token(&token, None).is_err());
+    }
+
+    #[test]
+    fn test_key_policy() {
+        let (issuer, validator) = get_fixture();
+        let validator = validator.with_key_policy(KeyPolicy::default()).unwrap();
+        let token = id_token(&issuer).sign();
+        assert!(validator.validate_id_token(&token, None).is_ok());
+
+        let (_, validator) = get_fixture();
+        let policy = KeyPolicy::default().with_min_rsa_bits(4096);
+        assert!(validator.with_key_policy(policy).is_err());
+
+        // the token alg must match the alg of the JWK
+        let mut jwks = get_jwks(&issuer);
+        jwks["keys"][0]["alg"] = json!("RS512");
+        let discovery =
+            OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, &get_discovery_json()).unwrap();
+        let validator = OidcClientValidator::new(TEAM_NAME, CLIENT_ID, discovery, &jwks)
+            .unwrap()
+            .with_clock(Arc::new(FakeClock::new(ISSUED_AT)));
+        assert!(validator.validate_id_token(&token, None).is_ok());
+
+        let validator = validator.with_key_policy(KeyPolicy::default()).unwrap();
+        let result = validator.validate_id_token(&token, None);
+        assert_eq!(result.unwrap_err().get_kind(), "key_policy_violation");
+    }
+
+    #[test]
+    fn test_key_id_verification() {
+        let (_, validator) = get_fixture();
+        assert!(validator.with_key_id_verification().is_ok());
+
+        let issuer = MockIssuer::new(TEAM_NAME);
+        let mut jwks = get_jwks(&issuer);
+        jwks["keys"][0]["kid"] = json!("oidc-mock-key-1");
+        let discovery =
+            OidcDiscovery::from_json(TEAM_NAME, CLIENT_ID, &get_discovery_json()).unwrap();
+        let validator = OidcClientValidator::new(TEAM_NAME, CLIENT_ID, discovery, &jwks).unwrap();
+        assert!(validator.with_key_id_verification().is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{keys, thumbprint};
+
+use std::{collections::HashSet, fmt};
+
+/// A callback invoked for every anomaly detected in a key rotation.
+pub type AlertHook = Box<dyn Fn(&RotationAnomaly) + Send + Sync>;
+
+/// A suspicious property of a candidate key set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RotationAnomaly {
+    /// None of the currently trusted keys remain in the candidate key set.
+    FullReplacement { latest_key_id: String },
+    /// The candidate latest key was never published in an earlier key set,
+    /// so it became the latest key without an overlap period.
+    UnseenLatestKey { key_id: String },
+    /// A candidate key matches neither a pinned kid nor a pinned thumbprint.
+    UnpinnedKey { key_id: String },
+}
+
+impl fmt::Display for RotationAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RotationAnomaly::FullReplacement { latest_key_id } => write!(
+                f,
+                "every trusted key was replaced, new latest kid '{latest_key_id}'"
+            ),
+            RotationAnomaly::UnseenLatestKey { key_id } => {
+                write!(f, "latest kid '{key_id}' was never seen before")
+            }
+            RotationAnomaly::UnpinnedKey { key_id } => write!(f, "kid '{key_id}' is not pinned"),
+        }
+    }
+}
+
+/// What to do with a rotation once an anomaly has been detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RotationAction {
+    /// Raise alerts and apply the rotation anyway.
+    #[default]
+    Flag,
+    /// Raise alerts and keep trusting the current keys until the rotation is approved,
+    /// see `Cache::approve_held_rotation`.
+    Hold,
+}
+
+/// Inspects key rotations for pinning violations and, in trust-on-first-use mode,
+/// for rotations that look unlike a routine Cloudflare key roll.
+/// The initial key set is always trusted.
+pub struct RotationGuard {
+    action: RotationAction,
+    pins: HashSet<String>,
+    tofu: bool,
+    alert_hook: Option<AlertHook>,
+}
+
+impl RotationGuard {
+    /// Constructs a RotationGuard with no pins and trust-on-first-use checks disabled.
+    pub fn new(action: RotationAction) -> Self {
+        RotationGuard {
+            action,
+            pins: HashSet::new(),
+            tofu: false,
+            alert_hook: None,
+        }
+    }
+
+    /// Only trusts keys whose kid or thumbprint is in the given set.
+    pub fn with_pinned_keys(mut self, pins: &[&str]) -> Self {
+        self.pins = pins.iter().map(|pin| pin.to_lowercase()).collect();
+        self
+    }
+
+    /// Detects full key set replacements and latest keys that were never seen before.
+    pub fn with_tofu(mut self) -> Self {
+        self.tofu = true;
+        self
+    }
+
+    /// Registers a callback to be invoked for every detected anomaly.
+    pub fn with_alert_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&RotationAnomaly) + Send + Sync + 'static,
+    {
+        self.alert_hook = Some(Box::new(hook));
+        self
+    }
+
+    fn is_pinned(&self, access_key: &dyn keys::AccessKey) -> bool {
+        let thumbprints = thumbprint::get_thumbprints(access_key).unwrap_or_default();
+
+        std::iter::once(access_key.get_key_id())
+            .chain(thumbprints)
+            .any(|candidate| self.pins.contains(&candidate.to_lowercase()))
+    }
+
+    /// Returns the anomalies of rotating from the current key IDs to a candidate key set.
+    /// `seen_key_ids` holds every key ID trusted before, including the current ones.
+    pub fn inspect(
+        &self,
+        current_key_ids: &HashSet<String>,
+        seen_key_ids: &HashSet<String>,
+        latest_key_id: &str,
+        keymap: &keys::AccessKeyMap,
+    ) -> Vec<RotationAnomaly> {
+        let mut anomalies: Vec<RotationAnomaly> = Vec::new();
+
+        if !self.pins.is_empty() {
+            let mut unpinned: Vec<&String> = keymap
+                .iter()
+                .filter(|(_, access_key)| !self.is_pinned(access_key.as_ref()))
+                .map(|(key_id, _)| key_id)
+                .collect();
+            unpinned.sort();
+
+            for key_id in unpinned {
+                anomalies.push(RotationAnomaly::UnpinnedKey {
+                    key_id: key_id.clone(),
+                });
+            }
+        }
+
+        if self.tofu {
+            if current_key_ids
+                .iter()
+                .all(|key_id| !keymap.contains_key(key_id))
+            {
+                anomalies.push(RotationAnomaly::FullReplacement {
+                    latest_key_id: latest_key_id.to_string(),
+                });
+            }
+
+            if !seen_key_ids.contains(latest_key_id) {
+                anomalies.push(RotationAnomaly::UnseenLatestKey {
+                    key_id: latest_key_id.to_string(),
+                });
+            }
+        }
+
+        anomalies
+    }
+
+    /// Raises an alert for each anomaly and returns whether the rotation may be applied.
+    pub(crate) fn permits(&self, anomalies: &[RotationAnomaly]) -> bool {
+        if let Some(hook) = &self.alert_hook {
+            anomalies.iter().for_each(hook);
+        }
+
+        anomalies.is_empty() || self.action == RotationAction::Flag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+    use std::sync::{Arc, Mutex};
+
+    const SAMPLE_PAYLOAD: &str = include_str!("../test_data/sample_signing_keys.json");
+    const ROTATION_PAYLOAD: &str = include_str!("../test_data/mock_signing_key_2.json");
+    const LATEST_KEY_ID: &str = "a5ea8bd1b94cadf2a5f0f47dad188e6aafbcd28eeab2e71b11ddd96d9cc28c69";
+    const ADDITIONAL_KEY_ID: &str =
+        "1112fda21ace0ef9f8be527697e87970566d4e4dfab130d9a9d7a0748d3da8dd";
+    const ROTATION_KEY_ID: &str = "X33sNdmTvRC0O6irH8lKcncS9klV37WVzKlV7v2zY_s";
+
+    fn load_keymap(text: &str) -> keys::AccessKeyMap {
+        let payload: serde_json::Value = serde_json::from_str(text).unwrap();
+        api::extract_current_keys(&payload).unwrap()
+    }
+
+    fn get_key_ids(key_ids: &[&str]) -> HashSet<String> {
+        key_ids.iter().map(|key_id| key_id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_pinned_keys() {
+        let keymap = load_keymap(SAMPLE_PAYLOAD);
+        let current = get_key_ids(&[LATEST_KEY_ID, ADDITIONAL_KEY_ID]);
+
+        let guard = RotationGuard::new(RotationAction::Hold)
+            .with_pinned_keys(&[LATEST_KEY_ID, ADDITIONAL_KEY_ID]);
+        assert!(guard
+            .inspect(&current, &current, LATEST_KEY_ID, &keymap)
+            .is_empty());
+
+        let guard = RotationGuard::new(RotationAction::Hold).with_pinned_keys(&[LATEST_KEY_ID]);
+        let anomalies = guard.inspect(&current, &current, LATEST_KEY_ID, &keymap);
+        assert_eq!(
+            anomalies,
+            vec![RotationAnomaly::UnpinnedKey {
+                key_id: ADDITIONAL_KEY_ID.to_string()
+            }]
+        );
+        assert!(!guard.permits(&anomalies));
+    }
+
+    #[test]
+    fn test_tofu() {
+        let current = get_key_ids(&[LATEST_KEY_ID, ADDITIONAL_KEY_ID]);
+        let keymap = load_keymap(ROTATION_PAYLOAD);
+
+        let alerts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = alerts.clone();
+        let guard = RotationGuard::new(RotationAction::Flag)
+            .with_tofu()
+            .with_alert_hook(move |anomaly| sink.lock().unwrap().push(anomaly.to_string()));
+
+        let anomalies = guard.inspect(&current, &current, ROTATION_KEY_ID, &keymap);
+        assert_eq!(anomalies.len(), 2);
+        assert!(guard.permits(&anomalies));
+        assert_eq!(alerts.lock().unwrap().len(), 2);
+
+        // a latest key published in an earlier key set is a routine rotation
+        let mut seen = current.clone();
+        seen.insert(ROTATION_KEY_ID.to_string());
+        let mut keymap = load_keymap(SAMPLE_PAYLOAD);
+        keymap.extend(load_keymap(ROTATION_PAYLOAD));
+        assert!(guard
+            .inspect(&current, &seen, ROTATION_KEY_ID, &keymap)
+            .is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::DecodedToken;
+
+use std::str::FromStr;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+fn get_str_claim<'a>(token_data: &'a DecodedToken, key: &str) -> Option<&'a str> {
+    token_data.claims.get(key)?.as_str()
+}
+
+fn contains_ignore_case(values: &[String], candidate: &str) -> bool {
+    values
+        .iter()
+        .any(|value| value.eq_ignore_ascii_case(candidate))
+}
+
+fn custom_value_matches(value: &Value, values: &[String]) -> bool {
+    match value {
+        Value::String(string) => values.contains(string),
+        Value::Array(items) => items.iter().any(|item| custom_value_matches(item, values)),
+        Value::Bool(_) | Value::Number(_) => values.contains(&value.to_string()),
+        _ => false,
+    }
+}
+
+// an empty combinator would match every token for `all`, so both are rejected
+fn deserialize_rules<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Rule>, D::Error> {
+    let rules = Vec::<Rule>::deserialize(deserializer)?;
+
+    match rules.is_empty() {
+        true => Err(D::Error::custom("combinators require at least one rule")),
+        false => Ok(rules),
+    }
+}
+
+/// A single authorization rule evaluated against the claims of a validated token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Rule {
+    /// Matches when the `email` claim belongs to one of the listed domains.
+    EmailDomain { domains: Vec<String> },
+    /// Matches when the `country` claim is one of the listed ISO-3166 codes.
+    CountryAllow { countries: Vec<String> },
+    /// Matches when the `country` claim is present and not one of the listed codes.
+    CountryDeny { countries: Vec<String> },
+    /// Matches when the named field of the `custom` claim equals one of the values,
+    /// or for array fields, contains one of them.
+    CustomField { field: String, values: Vec<String> },
+    /// Matches when the `common_name` claim of a service token is one of the listed names.
+    ServiceToken { common_names: Vec<String> },
+    /// Matches when at least one nested rule matches.
+    Any {
+        #[serde(deserialize_with = "deserialize_rules")]
+        rules: Vec<Rule>,
+    },
+    /// Matches when every nested rule matches.
+    All {
+        #[serde(deserialize_with = "deserialize_rules")]
+        rules: Vec<Rule>,
+    },
+    /// Inverts the nested rule.
+    Not { rule: Box<Rule> },
+}
+
+impl Rule {
+    fn describe(&self) -> String {
+        match self {
+            Rule::EmailDomain { domains } => format!("email domain in [{}]", domains.join(", ")),
+            Rule::CountryAllow { countries } => format!("country in [{}]", countries.join(", ")),
+            Rule::CountryDeny { countries } => {
+                format!("country not in [{}]", countries.join(", "))
+            }
+            Rule::CustomField { field, values } => {
+                format!("custom.{field} in [{}]", values.join(", "))
+            }
+            Rule::ServiceToken { common_names } => {
+                format!("service token common_name in [{}]", common_names.join(", "))
+            }
+            Rule::Any { .. } => "any".to_string(),
+            Rule::All { .. } => "all".to_string(),
+            Rule::Not { .. } => "not".to_string(),
+        }
+    }
+
+    fn is_match(&self, token_data: &DecodedToken) -> bool {
+        match self {
+            Rule::EmailDomain { domains } => get_str_claim(token_data, "email")
+                .and_then(|email| email.rsplit_once('@'))
+                .is_some_and(|(_, domain)| contains_ignore_case(domains, domain)),
+            Rule::CountryAllow { countries } => get_str_claim(token_data, "country")
+                .is_some_and(|country| contains_ignore_case(countries, country)),
+            Rule::CountryDeny { countries } => get_str_claim(token_data, "country")
+                .is_some_and(|country| !contains_ignore_case(countries, country)),
+            Rule::CustomField { field, values } => token_data
+                .claims
+                .get("custom")
+                .and_then(|custom| custom.get(field))
+                .is_some_and(|value| custom_value_matches(value, values)),
+            Rule::ServiceToken { common_names } => get_str_claim(token_data, "common_name")
+                .is_some_and(|common_name| common_names.iter().any(|name| name == common_name)),
+            Rule::Any { .. } | Rule::All { .. } | Rule::Not { .. } => {
+                unreachable!("combinators are evaluated by Rule::evaluate")
+            }
+        }
+    }
+
+    /// Evaluates the rule against a validated token, recording the outcome of
+    /// every nested rule.
+    pub fn evaluate(&self, token_data: &DecodedToken) -> Evaluation {
+        let children: Vec<Evaluation> = match self {
+            Rule::Any { rules } | Rule::All { rules } => {
+                rules.iter().map(|rule| rule.evaluate(token_data)).collect()
+            }
+            Rule::Not { rule } => vec![rule.evaluate(token_data)],
+            _ => Vec::new(),
+        };
+
+        let matched = match self {
+            Rule::Any { .. } => children.iter().any(|child| child.matched),
+            Rule::All { .. } => children.iter().all(|child| child.matched),
+            Rule::Not { .. } => !children[0].matched,
+            rule => rule.is_match(token_data),
+        };
+
+        Evaluation {
+            description: self.describe(),
+            matched,
+            negated: matches!(self, Rule::Not { .. }),
+            children,
+        }
+    }
+}
+
+/// The outcome of evaluating a Rule, including the outcome of any nested rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub description: String,
+    pub matched: bool,
+    /// Set for `not` rules, whose nested rules matched only if the `not` did not.
+    pub negated: bool,
+    pub children: Vec<Evaluation>,
+}
+
+impl Evaluation {
+    fn explain_into(&self, depth: usize, lines: &mut Vec<String>) {
+        let outcome = if self.matched {
+            "matched"
+        } else {
+            "not matched"
+        };
+        lines.push(format!(
+            "{}{}: {outcome}",
+            "  ".repeat(depth),
+            self.description
+        ));
+
+        for child in &self.children {
+            child.explain_into(depth + 1, lines);
+        }
+    }
+
+    /// Renders the evaluation tree as indented, human readable lines.
+    pub fn explain(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.explain_into(0, &mut lines);
+        lines
+    }
+
+    /// Returns the descriptions of the leaf rules that contributed to a match.
+    /// Rules within unmatched combinators or below a `not` are never reported.
+    pub fn matched_rules(&self) -> Vec<String> {
+        if !self.matched || self.negated {
+            return Vec::new();
+        }
+
+        if self.children.is_empty() {
+            return vec![self.description.clone()];
+        }
+
+        self.children
+            .iter()
+            .flat_map(|child| child.matched_rules())
+            .collect()
+    }
+}
+
+/// A named, serde-loadable authorization policy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    pub name: String,
+    pub rule: Rule,
+}
+
+impl Policy {
+    /// Constructs a new Policy from a name and a root rule.
+    pub fn new(name: &str, rule: Rule) -> Self {
+        Policy {
+            name: name.to_string(),
+            rule,
+        }
+    }
+
+    /// Evaluates the policy against a token returned by `Validator.validate_token()`.
+    pub fn evaluate(&self, token_data: &DecodedToken) -> Evaluation {
+        self.rule.evaluate(token_data)
+    }
+
+    /// Returns true if the policy permits the given token.
+    pub fn is_allowed(&self, token_data: &DecodedToken) -> bool {
+        self.evaluate(token_data).matched
+    }
+}
+
+impl FromStr for Policy {
+    type Err = serde_json::Error;
+
+    /// Attempts to load a Policy from a given JSON string slice.
+    fn from_str(json_str: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(json_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const POLICY_JSON: &str = r#"{
+        "name": "staff",
+        "rule": {
+            "type": "any",
+            "rules": [
+                {
+                    "type": "all",
+                    "rules": [
+                        {"type": "email_domain", "domains": ["jacobtaylor.id.au"]},
+                        {"type": "country_deny", "countries": ["KP", "IR"]},
+                        {"type": "not", "rule": {"type": "custom_field", "field": "groups", "values": ["suspended"]}}
+                    ]
+                },
+                {"type": "service_token", "common_names": ["ci.access"]}
+            ]
+        }
+    }"#;
+
+    fn get_token_data(claims: Value) -> DecodedToken {
+        DecodedToken {
+            header: jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            claims,
+        }
+    }
+
+    fn get_app_token(email: &str, country: &str, groups: &[&str]) -> DecodedToken {
+        get_token_data(json!({
+            "email": email,
+            "country": country,
+            "custom": {"groups": groups},
+        }))
+    }
+
+    #[test]
+    fn test_policy_from_str() {
+        let policy = Policy::from_str(POLICY_JSON).unwrap();
+
+        assert_eq!(policy.name, "staff");
+        match &policy.rule {
+            Rule::Any { rules } => assert_eq!(rules.len(), 2),
+            _ => panic!("unexpected root rule"),
+        }
+    }
+
+    #[test]
+    fn test_policy_empty_combinator() {
+        for rule_type in ["all", "any"] {
+            let json_str =
+                format!(r#"{{"name": "empty", "rule": {{"type": "{rule_type}", "rules": []}}}}"#);
+            assert!(Policy::from_str(&json_str).is_err());
+        }
+    }
+
+    #[test]
+    fn test_policy_evaluate() {
+        let policy = Policy::from_str(POLICY_JSON).unwrap();
+
+        assert!(policy.is_allowed(&get_app_token("me@jacobtaylor.id.au", "AU", &["eng"])));
+        assert!(policy.is_allowed(&get_app_token("me@JacobTaylor.id.au", "US", &[])));
+        assert!(!policy.is_allowed(&get_app_token("me@example.com", "AU", &["eng"])));
+        assert!(!policy.is_allowed(&get_app_token("me@jacobtaylor.id.au", "KP", &[])));
+        assert!(!policy.is_allowed(&get_app_token(
+            "me@jacobtaylor.id.au",
+            "AU",
+            &["eng", "suspended"]
+        )));
+
+        let service_token = get_token_data(json!({"common_name": "ci.access", "sub": ""}));
+        assert!(policy.is_allowed(&service_token));
+
+        let service_token = get_token_data(json!({"common_name": "other.access", "sub": ""}));
+        assert!(!policy.is_allowed(&service_token));
+    }
+
+    #[test]
+    fn test_evaluation_explain() {
+        let policy = Policy::from_str(POLICY_JSON).unwrap();
+        let evaluation = policy.evaluate(&get_app_token("me@jacobtaylor.id.au", "AU", &[]));
+
+        assert!(evaluation.matched);
+        assert_eq!(
+            evaluation.matched_rules(),
+            vec![
+                "email domain in [jacobtaylor.id.au]",
+                "country not in [KP, IR]",
+            ]
+        );
+        assert_eq!(
+            evaluation.explain(),
+            vec![
+                "any: matched",
+                "  all: matched",
+                "    email domain in [jacobtaylor.id.au]: matched",
+                "    country not in [KP, IR]: matched",
+                "    not: matched",
+                "      custom.groups in [suspended]: not matched",
+                "  service token common_name in [ci.access]: not matched",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluation_matched_rules() {
+        let policy = Policy::from_str(POLICY_JSON).unwrap();
+
+        // a denied token reports no matches, including those below a failed all or not
+        let evaluation =
+            policy.evaluate(&get_app_token("me@jacobtaylor.id.au", "AU", &["suspended"]));
+        assert!(!evaluation.matched);
+        assert!(evaluation.matched_rules().is_empty());
+
+        // only the branch that matched is reported
+        let service_token = get_token_data(json!({"common_name": "ci.access", "country": "AU"}));
+        assert_eq!(
+            policy.evaluate(&service_token).matched_rules(),
+            vec!["service token common_name in [ci.access]"]
+        );
+    }
+}
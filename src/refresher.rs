@@ -0,0 +1,265 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    StdResult, Validator,
+};
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+#[derive(Default)]
+struct RefreshState {
+    sync_count: usize,
+    last_attempt: Option<SystemTime>,
+    last_sync: Option<SystemTime>,
+    last_error: Option<String>,
+}
+
+/// Periodically syncs a Validator with the CF API, or whichever certs endpoint it is configured with.
+///
+/// Syncs can be triggered manually with `tick`, or from a background thread with `spawn`.
+/// A failed sync keeps the current keys, and is retried at the next interval.
+#[derive(Clone)]
+pub struct Refresher {
+    validator: Arc<dyn Validator>,
+    interval: Duration,
+    clock: Arc<dyn Clock>,
+    state: Arc<Mutex<RefreshState>>,
+}
+
+impl Refresher {
+    /// Constructs a Refresher syncing a Validator at a given interval.
+    pub fn new(validator: Arc<dyn Validator>, interval: Duration) -> Self {
+        Refresher {
+            validator,
+            interval,
+            clock: Arc::new(SystemClock),
+            state: Arc::default(),
+        }
+    }
+
+    /// Schedules syncs with the given Clock instead of the system time.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the refreshed Validator.
+    pub fn get_validator(&self) -> Arc<dyn Validator> {
+        self.validator.clone()
+    }
+
+    /// Syncs the Validator once. Returns a wrapped bool signalling if the keys were updated.
+    pub fn tick(&self) -> StdResult<bool> {
+        let result = self.validator.sync();
+        let now = self.clock.now();
+
+        let mut state = self.state.lock().unwrap();
+        state.sync_count += 1;
+        state.last_attempt = Some(now);
+        match &result {
+            Ok(_) => {
+                state.last_sync = Some(now);
+                state.last_error = None;
+            }
+            Err(err) => state.last_error = Some(err.to_string()),
+        }
+
+        result
+    }
+
+    /// Returns the time until the next sync is due, zero if it is due now.
+    pub fn get_time_until_due(&self) -> Duration {
+        match self.state.lock().unwrap().last_attempt {
+            Some(last_attempt) => (last_attempt + self.interval)
+                .duration_since(self.clock.now())
+                .unwrap_or_default(),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Returns whether a sync is due, i.e. no sync was attempted within the interval.
+    pub fn is_due(&self) -> bool {
+        self.get_time_until_due().is_zero()
+    }
+
+    /// Returns the time since the last successful sync, if any.
+    pub fn get_sync_age(&self) -> Option<Duration> {
+        let last_sync = self.state.lock().unwrap().last_sync?;
+        Some(
+            self.clock
+                .now()
+                .duration_since(last_sync)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Returns the number of syncs attempted.
+    pub fn get_sync_count(&self) -> usize {
+        self.state.lock().unwrap().sync_count
+    }
+
+    /// Returns the time of the last successful sync.
+    pub fn get_last_sync(&self) -> Option<SystemTime> {
+        self.state.lock().unwrap().last_sync
+    }
+
+    /// Returns the error of the last sync, if it failed.
+    pub fn get_last_error(&self) -> Option<String> {
+        self.state.lock().unwrap().last_error.clone()
+    }
+
+    /// Syncs the Validator on a background thread whenever a sync is due, starting immediately,
+    /// until the returned handle is stopped or dropped. Failed syncs are retried after the interval.
+    pub fn spawn(&self) -> RefresherHandle {
+        let stop: Arc<(Mutex<bool>, Condvar)> = Arc::default();
+
+        let refresher = self.clone();
+        let stopping = stop.clone();
+        let handle = thread::spawn(move || {
+            let (stopped, condvar) = &*stopping;
+
+            loop {
+                if refresher.is_due() {
+                    let _ = refresher.tick();
+                }
+
+                // a Clock that does not follow the system time is checked again after the interval
+                let wait = match refresher.get_time_until_due() {
+                    wait if wait.is_zero() => refresher.interval,
+                    wait => wait,
+                };
+
+                let guard = stopped.lock().unwrap();
+                let (guard, _) = condvar
+                    .wait_timeout_while(guard, wait, |stopped| !*stopped)
+                    .unwrap();
+                if *guard {
+                    break;
+                }
+            }
+        });
+
+        RefresherHandle {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+/// Stops a spawned Refresher when stopped or dropped.
+pub struct RefresherHandle {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RefresherHandle {
+    /// Stops the background thread and waits for it to exit.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        let (stopped, condvar) = &*self.stop;
+        *stopped.lock().unwrap() = true;
+        condvar.notify_all();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for RefresherHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::FakeClock,
+        mock_server::{MockCertsServer, MockResponse},
+        testing::MockIssuer,
+        TeamValidator,
+    };
+
+    const TEAM_NAME: &str = "molten";
+
+    fn get_refresher(server: &MockCertsServer, issuer: &MockIssuer) -> Refresher {
+        let validator = TeamValidator::from_team_keys(issuer.to_team_keys())
+            .with_certs_url(&server.get_certs_url(TEAM_NAME));
+        Refresher::new(Arc::new(validator), Duration::from_millis(20))
+    }
+
+    #[test]
+    fn test_tick() {
+        let server = MockCertsServer::start();
+        let mut issuer = MockIssuer::new(TEAM_NAME);
+        server.publish(&issuer);
+        let refresher = get_refresher(&server, &issuer);
+
+        assert!(!refresher.tick().unwrap());
+        issuer.rotate();
+        server.publish(&issuer);
+        assert!(refresher.tick().unwrap());
+
+        server.push_response(TEAM_NAME, MockResponse::Error(503));
+        assert!(refresher.tick().is_err());
+        assert!(refresher.get_last_error().is_some());
+        assert_eq!(refresher.get_sync_count(), 3);
+
+        assert!(!refresher.tick().unwrap());
+        assert!(refresher.get_last_error().is_none());
+    }
+
+    #[test]
+    fn test_schedule() {
+        let server = MockCertsServer::start();
+        let issuer = MockIssuer::new(TEAM_NAME);
+        server.publish(&issuer);
+        let clock = Arc::new(FakeClock::new(1717979639));
+        let refresher = get_refresher(&server, &issuer).with_clock(clock.clone());
+
+        assert!(refresher.is_due());
+        assert!(refresher.get_sync_age().is_none());
+        refresher.tick().unwrap();
+        assert!(!refresher.is_due());
+        assert_eq!(refresher.get_time_until_due(), Duration::from_millis(20));
+
+        clock.advance(Duration::from_millis(15));
+        assert_eq!(refresher.get_time_until_due(), Duration::from_millis(5));
+        assert_eq!(refresher.get_sync_age(), Some(Duration::from_millis(15)));
+
+        // failed syncs are retried after the interval, without refreshing the sync age
+        clock.advance(Duration::from_millis(5));
+        assert!(refresher.is_due());
+        server.push_response(TEAM_NAME, MockResponse::Error(503));
+        assert!(refresher.tick().is_err());
+        assert!(!refresher.is_due());
+        assert_eq!(refresher.get_sync_age(), Some(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_spawn() {
+        let server = MockCertsServer::start();
+        let issuer = MockIssuer::new(TEAM_NAME);
+        server.publish(&issuer);
+        let refresher = get_refresher(&server, &issuer);
+
+        let handle = refresher.spawn();
+        thread::sleep(Duration::from_millis(100));
+        handle.stop();
+
+        let sync_count = refresher.get_sync_count();
+        assert!(sync_count >= 2);
+        assert!(refresher.get_last_sync().is_some());
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(refresher.get_sync_count(), sync_count);
+    }
+}
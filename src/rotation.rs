@@ -0,0 +1,255 @@
+use crate::{
+    inspect, mock_server::MockCertsServer, refresher::Refresher, testing::MockIssuer, Validator,
+};
+
+use std::{thread, time::Duration};
+
+use jsonwebtoken::Algorithm;
+
+/// The phases of a CF key rotation, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationPhase {
+    /// Only the old key is published, and signs new tokens.
+    Initial,
+    /// The new key is published alongside the old key, which still signs new tokens.
+    Published,
+    /// The new key is promoted to `public_cert`, and signs new tokens.
+    Promoted,
+    /// The old key is no longer published.
+    Retired,
+}
+
+impl RotationPhase {
+    fn next(self) -> Option<Self> {
+        match self {
+            RotationPhase::Initial => Some(RotationPhase::Published),
+            RotationPhase::Published => Some(RotationPhase::Promoted),
+            RotationPhase::Promoted => Some(RotationPhase::Retired),
+            RotationPhase::Retired => None,
+        }
+    }
+}
+
+/// Drives a MockIssuer through a key rotation, serving each phase from a MockCertsServer,
+/// so that applications can assert which tokens their validators accept at each step.
+///
+/// Point a TeamValidator at `get_certs_url`, then either step through the phases with
+/// `advance` or let `run` drive the whole timeline against a Refresher.
+pub struct RotationScenario {
+    issuer: MockIssuer,
+    server: MockCertsServer,
+    phase: RotationPhase,
+    old_key_id: String,
+    new_key_id: Option<String>,
+    phase_duration: Option<Duration>,
+}
+
+impl RotationScenario {
+    /// Constructs a RotationScenario for a team, in the initial phase.
+    pub fn new(team_name: &str) -> Self {
+        let issuer = MockIssuer::new(team_name);
+        let server = MockCertsServer::start();
+        server.publish(&issuer);
+
+        RotationScenario {
+            old_key_id: issuer.get_latest_key_id(),
+            issuer,
+            server,
+            phase: RotationPhase::Initial,
+            new_key_id: None,
+            phase_duration: None,
+        }
+    }
+
+    /// Holds each phase for a duration when run, for use with a spawned Refresher.
+    /// Without it, `run` ticks the Refresher once per phase.
+    pub fn with_phase_duration(mut self, phase_duration: Duration) -> Self {
+        self.phase_duration = Some(phase_duration);
+        self
+    }
+
+    /// Returns the certs URL serving the keys of the current phase.
+    pub fn get_certs_url(&self) -> String {
+        self.server.get_certs_url(&self.issuer.get_team_name())
+    }
+
+    /// Returns the underlying MockIssuer.
+    pub fn get_issuer(&self) -> &MockIssuer {
+        &self.issuer
+    }
+
+    /// Returns the current phase.
+    pub fn get_phase(&self) -> RotationPhase {
+        self.phase
+    }
+
+    /// Returns the kid of the key being rotated out.
+    pub fn get_old_key_id(&self) -> String {
+        self.old_key_id.clone()
+    }
+
+    /// Returns the kid of the key being rotated in, once published.
+    pub fn get_new_key_id(&self) -> Option<String> {
+        self.new_key_id.clone()
+    }
+
+    /// Moves to the next phase and serves its keys.
+    /// Returns the new phase, or None if the rotation is complete.
+    pub fn advance(&mut self) -> Option<RotationPhase> {
+        let phase = self.phase.next()?;
+
+        match phase {
+            RotationPhase::Initial => {}
+            RotationPhase::Published => self.new_key_id = Some(self.issuer.publish_key()),
+            RotationPhase::Promoted => {
+                let new_key_id = self.new_key_id.clone().unwrap_or_default();
+                self.issuer.promote(&new_key_id);
+            }
+            RotationPhase::Retired => {
+                self.issuer.retire(&self.old_key_id);
+            }
+        }
+
+        self.server.publish(&self.issuer);
+        self.phase = phase;
+        Some(phase)
+    }
+
+    /// Signs an application token for an audience with a specific key.
+    pub fn mint(&self, key_id: &str, audience: &str) -> String {
+        self.issuer.app_token(audience).with_key_id(key_id).sign()
+    }
+
+    /// Returns whether a validator accepts a token for an audience signed with a specific key.
+    pub fn is_accepted(&self, validator: &dyn Validator, key_id: &str, audience: &str) -> bool {
+        let team_name = self.issuer.get_team_name();
+        let mut constraints = jsonwebtoken::Validation::new(Algorithm::RS256);
+        constraints.set_audience(&[audience]);
+        constraints.set_issuer(&[inspect::get_team_issuer(&team_name)]);
+
+        validator
+            .validate_token(&self.mint(key_id, audience), &team_name, &mut constraints)
+            .is_ok()
+    }
+
+    /// Runs the remaining phases, calling `check` once the Refresher has had the chance
+    /// to sync each phase, including the current one.
+    pub fn run<F>(&mut self, refresher: &Refresher, mut check: F)
+    where
+        F: FnMut(&RotationScenario, &dyn Validator),
+    {
+        let validator = refresher.get_validator();
+
+        loop {
+            match self.phase_duration {
+                Some(phase_duration) => thread::sleep(phase_duration),
+                None => {
+                    let _ = refresher.tick();
+                }
+            }
+
+            check(self, validator.as_ref());
+
+            if self.advance().is_none() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TeamValidator;
+    use std::sync::Arc;
+
+    const TEAM_NAME: &str = "molten";
+    const AUDIENCE: &str = "mock-audience";
+
+    fn get_refresher(scenario: &RotationScenario, interval: Duration) -> Refresher {
+        let validator = TeamValidator::from_team_keys(scenario.get_issuer().to_team_keys())
+            .with_certs_url(&scenario.get_certs_url());
+        Refresher::new(Arc::new(validator), interval)
+    }
+
+    fn check_phase(
+        scenario: &RotationScenario,
+        validator: &dyn Validator,
+        phases: &mut Vec<RotationPhase>,
+    ) {
+        let old_key_id = scenario.get_old_key_id();
+        let old_accepted = scenario.is_accepted(validator, &old_key_id, AUDIENCE);
+
+        match scenario.get_phase() {
+            RotationPhase::Initial => {
+                assert!(old_accepted);
+                assert!(scenario.get_new_key_id().is_none());
+            }
+            RotationPhase::Published | RotationPhase::Promoted => {
+                let new_key_id = scenario.get_new_key_id().unwrap();
+                assert!(old_accepted);
+                assert!(scenario.is_accepted(validator, &new_key_id, AUDIENCE));
+            }
+            RotationPhase::Retired => {
+                let new_key_id = scenario.get_new_key_id().unwrap();
+                assert!(!old_accepted);
+                assert!(scenario.is_accepted(validator, &new_key_id, AUDIENCE));
+            }
+        }
+
+        phases.push(scenario.get_phase());
+    }
+
+    #[test]
+    fn test_rotation_ticked() {
+        let mut scenario = RotationScenario::new(TEAM_NAME);
+        let refresher = get_refresher(&scenario, Duration::from_secs(60));
+
+        let mut phases = Vec::new();
+        scenario.run(&refresher, |scenario, validator| {
+            check_phase(scenario, validator, &mut phases)
+        });
+
+        assert_eq!(
+            phases,
+            vec![
+                RotationPhase::Initial,
+                RotationPhase::Published,
+                RotationPhase::Promoted,
+                RotationPhase::Retired,
+            ]
+        );
+        assert_eq!(refresher.get_sync_count(), 4);
+        assert!(scenario.advance().is_none());
+    }
+
+    #[test]
+    fn test_rotation_spawned() {
+        let mut scenario =
+            RotationScenario::new(TEAM_NAME).with_phase_duration(Duration::from_millis(150));
+        let refresher = get_refresher(&scenario, Duration::from_millis(10));
+
+        let handle = refresher.spawn();
+        let mut phases = Vec::new();
+        scenario.run(&refresher, |scenario, validator| {
+            check_phase(scenario, validator, &mut phases)
+        });
+        handle.stop();
+
+        assert_eq!(phases.len(), 4);
+        assert!(refresher.get_last_error().is_none());
+    }
+
+    #[test]
+    fn test_rotation_unsynced() {
+        let mut scenario = RotationScenario::new(TEAM_NAME);
+        let validator = TeamValidator::from_team_keys(scenario.get_issuer().to_team_keys());
+
+        // without a refresher, tokens signed with the new key are rejected
+        scenario.advance();
+        scenario.advance();
+        let new_key_id = scenario.get_new_key_id().unwrap();
+        assert!(!scenario.is_accepted(&validator, &new_key_id, AUDIENCE));
+        assert!(scenario.is_accepted(&validator, &scenario.get_old_key_id(), AUDIENCE));
+    }
+}
@@ -0,0 +1,403 @@
+use crate::{
+    errors::{ValidationError, ValidationResult},
+    DecodedToken, Validator,
+};
+
+use std::collections::HashMap;
+
+use jsonwebtoken::Validation;
+
+fn strip_port(host: &str) -> &str {
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+fn normalise_prefix(path_prefix: &str) -> String {
+    let trimmed = path_prefix.trim_end_matches('/');
+
+    // "/" normalises to "" and matches every path
+    match trimmed.is_empty() || trimmed.starts_with('/') {
+        true => trimmed.to_string(),
+        false => format!("/{trimmed}"),
+    }
+}
+
+fn percent_decode(path: &str) -> Option<String> {
+    let mut bytes = path.bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(path.len());
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'%' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            byte => decoded.push(byte),
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+// Characters that upstreams may or may not decode before routing, so that
+// a gateway and its upstream could disagree on the path of a request.
+const AMBIGUOUS_ESCAPES: [&str; 3] = ["%2f", "%5c", "%2e"];
+
+// Decodes a request path and collapses empty segments. Rather than resolving them,
+// paths with "." or ".." segments, backslashes, or encoded "/", "\" or "." are
+// rejected, as upstreams may interpret them differently.
+fn normalise_path(path: &str) -> Option<String> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let lowercase = path.to_ascii_lowercase();
+
+    if AMBIGUOUS_ESCAPES
+        .iter()
+        .any(|escape| lowercase.contains(escape))
+    {
+        return None;
+    }
+
+    let decoded = percent_decode(path)?;
+    if decoded.contains('\\') {
+        return None;
+    }
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" => {}
+            "." | ".." => return None,
+            segment => segments.push(segment),
+        }
+    }
+
+    Some(format!("/{}", segments.join("/")))
+}
+
+/// Matches the Host header of a request, either exactly,
+/// by wildcard subdomain (`*.example.com`) or unconditionally (`*`).
+#[derive(Debug, Clone, PartialEq)]
+enum HostPattern {
+    Any,
+    Wildcard(String),
+    Exact(String),
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Self {
+        let pattern = strip_port(pattern.trim()).to_lowercase();
+
+        if pattern.is_empty() || pattern == "*" {
+            HostPattern::Any
+        } else if let Some(suffix) = pattern.strip_prefix("*.") {
+            HostPattern::Wildcard(format!(".{suffix}"))
+        } else {
+            HostPattern::Exact(pattern)
+        }
+    }
+
+    fn is_match(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Any => true,
+            HostPattern::Wildcard(suffix) => host.ends_with(suffix.as_str()),
+            HostPattern::Exact(name) => host == name,
+        }
+    }
+
+    fn specificity(&self) -> u8 {
+        match self {
+            HostPattern::Any => 0,
+            HostPattern::Wildcard(_) => 1,
+            HostPattern::Exact(_) => 2,
+        }
+    }
+}
+
+/// The outcome of resolving a request against an AudienceMap.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteTarget {
+    /// Requests must carry a token valid for the team and one of the audiences.
+    /// The optional profile names the constraints registered on the AudienceMap.
+    Protected {
+        team_name: String,
+        audiences: Vec<String>,
+        profile: Option<String>,
+    },
+    /// Requests are passed through without validation, e.g. `/healthz`.
+    Exempt,
+}
+
+/// Maps a host and path prefix onto a RouteTarget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    host: HostPattern,
+    path_prefix: String,
+    target: RouteTarget,
+}
+
+impl Route {
+    /// Constructs a Route requiring a token for the given team and audiences.
+    pub fn new(host: &str, path_prefix: &str, team_name: &str, audiences: &[&str]) -> Self {
+        Route {
+            host: HostPattern::parse(host),
+            path_prefix: normalise_prefix(path_prefix),
+            target: RouteTarget::Protected {
+                team_name: team_name.to_string(),
+                audiences: audiences.iter().map(|aud| aud.to_string()).collect(),
+                profile: None,
+            },
+        }
+    }
+
+    /// Constructs a Route that is exempt from validation.
+    pub fn exempt(host: &str, path_prefix: &str) -> Self {
+        Route {
+            host: HostPattern::parse(host),
+            path_prefix: normalise_prefix(path_prefix),
+            target: RouteTarget::Exempt,
+        }
+    }
+
+    /// Selects a named constraints profile for a protected Route.
+    pub fn with_profile(mut self, profile: &str) -> Self {
+        if let RouteTarget::Protected {
+            profile: ref mut current,
+            ..
+        } = self.target
+        {
+            *current = Some(profile.to_string());
+        }
+        self
+    }
+
+    /// Returns the target of the Route.
+    pub fn get_target(&self) -> &RouteTarget {
+        &self.target
+    }
+
+    fn is_match(&self, host: &str, path: &str) -> bool {
+        if !self.host.is_match(host) {
+            return false;
+        }
+
+        match path.strip_prefix(self.path_prefix.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+
+    fn specificity(&self) -> (usize, u8) {
+        (self.path_prefix.len(), self.host.specificity())
+    }
+}
+
+/// Selects the team, audiences and constraints applicable to a request
+/// for gateways fronting several Cloudflare Access applications.
+/// The most specific matching route wins: longer path prefixes beat shorter ones,
+/// then exact hosts beat wildcard hosts, which beat catch-all hosts.
+#[derive(Default)]
+pub struct AudienceMap {
+    routes: Vec<Route>,
+    profiles: HashMap<String, Validation>,
+}
+
+impl AudienceMap {
+    /// Adds a single Route to the AudienceMap.
+    pub fn add_route(&mut self, route: Route) {
+        self.routes.push(route);
+    }
+
+    /// Registers a named set of constraints that routes can refer to.
+    /// The audience of the constraints is replaced by the route audiences.
+    pub fn add_profile(&mut self, name: &str, constraints: Validation) {
+        self.profiles.insert(name.to_string(), constraints);
+    }
+
+    /// Finds the most specific Route matching a host and path.
+    /// The path is percent-decoded before matching. Paths with dot segments,
+    /// backslashes or encoded separators, which upstreams may route differently, match no Route.
+    pub fn resolve(&self, host: &str, path: &str) -> Option<&Route> {
+        let host = strip_port(host.trim()).to_lowercase();
+        let path = normalise_path(path)?;
+
+        self.routes
+            .iter()
+            .filter(|route| route.is_match(&host, &path))
+            .max_by_key(|route| route.specificity())
+    }
+
+    fn get_constraints(&self, profile: &Option<String>) -> ValidationResult<Validation> {
+        match profile {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or(ValidationError::unknown_profile(name)),
+            None => Ok(Validation::new(jsonwebtoken::Algorithm::RS256)),
+        }
+    }
+
+    /// Validates the token of a request against the constraints of its Route.
+    /// Returns `Ok(None)` for exempt routes.
+    pub fn validate_request(
+        &self,
+        validator: &dyn Validator,
+        host: &str,
+        path: &str,
+        token: Option<&str>,
+    ) -> ValidationResult<Option<DecodedToken>> {
+        let route = self
+            .resolve(host, path)
+            .ok_or(ValidationError::no_route(host, path))?;
+
+        match &route.target {
+            RouteTarget::Exempt => Ok(None),
+            RouteTarget::Protected {
+                team_name,
+                audiences,
+                profile,
+            } => {
+                let token = token.ok_or(ValidationError::missing_token())?;
+                let mut constraints = self.get_constraints(profile)?;
+                constraints.set_audience(audiences);
+
+                Ok(Some(validator.validate_token(
+                    token,
+                    team_name,
+                    &mut constraints,
+                )?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::FakeClock, testing::MockIssuer, TeamValidator};
+    use std::{sync::Arc, time::Duration};
+
+    const TEAM_NAME: &str = "molten";
+    const AUDIENCE: &str = "41f1d879c797d912d9bd80710db3dce92d30602a2dcbdf7bab33913071c44bd4";
+    const ISSUED_AT: u64 = 1717979639;
+
+    fn get_validator() -> (MockIssuer, TeamValidator, Arc<FakeClock>) {
+        let clock = Arc::new(FakeClock::new(ISSUED_AT));
+        let issuer = MockIssuer::new(TEAM_NAME).with_clock(clock.clone());
+        let validator =
+            TeamValidator::from_team_keys(issuer.to_team_keys()).with_clock(clock.clone());
+        (issuer, validator, clock)
+    }
+
+    fn get_audience_map() -> AudienceMap {
+        let mut constraints = Validation::new(jsonwebtoken::Algorithm::RS256);
+        constraints.validate_nbf = true;
+        constraints.leeway = 0;
+
+        let mut map = AudienceMap::default();
+        map.add_profile("strict", constraints);
+        map.add_route(Route::new("*", "/", TEAM_NAME, &["fallback"]));
+        map.add_route(
+            Route::new("*.molten.dev", "/", TEAM_NAME, &[AUDIENCE]).with_profile("strict"),
+        );
+        map.add_route(Route::new(
+            "app.molten.dev",
+            "/admin",
+            TEAM_NAME,
+            &["admin"],
+        ));
+        map.add_route(Route::exempt("*", "/healthz"));
+        map
+    }
+
+    fn get_audiences(route: &Route) -> Vec<String> {
+        match route.get_target() {
+            RouteTarget::Protected { audiences, .. } => audiences.clone(),
+            RouteTarget::Exempt => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let map = get_audience_map();
+
+        let route = map.resolve("app.molten.dev:443", "/admin/users").unwrap();
+        assert_eq!(get_audiences(route), vec!["admin"]);
+
+        let route = map.resolve("APP.molten.dev", "/administrator").unwrap();
+        assert_eq!(get_audiences(route), vec![AUDIENCE]);
+
+        let route = map.resolve("example.com", "/").unwrap();
+        assert_eq!(get_audiences(route), vec!["fallback"]);
+
+        let route = map.resolve("app.molten.dev", "/healthz").unwrap();
+        assert_eq!(route.get_target(), &RouteTarget::Exempt);
+
+        let route = map.resolve("app.molten.dev", "/healthz?verbose=1").unwrap();
+        assert_eq!(route.get_target(), &RouteTarget::Exempt);
+    }
+
+    #[test]
+    fn test_resolve_normalised_path() {
+        let map = get_audience_map();
+
+        for path in ["/%61dmin", "//admin", "/admin//users/"] {
+            let route = map.resolve("app.molten.dev", path).unwrap();
+            assert_eq!(get_audiences(route), vec!["admin"], "{path}");
+        }
+
+        for path in [
+            "/healthz/../admin",
+            "/healthz/%2e%2e/admin",
+            "/healthz/%2E%2E/admin/",
+            "/admin%2F..%2Fhealthz",
+            "/admin/%2e%2e/healthz",
+            "/admin%5c..%5chealthz",
+            "/admin\\..\\healthz",
+            "/./admin/users",
+            "/../healthz",
+            "/healthz/%2",
+            "/healthz/%zz",
+        ] {
+            assert!(map.resolve("app.molten.dev", path).is_none(), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_validate_request() {
+        let map = get_audience_map();
+        let (issuer, validator, clock) = get_validator();
+        let token = issuer.app_token(AUDIENCE).sign();
+
+        let result = map.validate_request(&validator, "app.molten.dev", "/", Some(&token));
+        assert!(result.unwrap().is_some());
+
+        // the profile of the route applies, without leeway
+        clock.advance(Duration::from_secs(3601));
+        let result = map.validate_request(&validator, "app.molten.dev", "/", Some(&token));
+        assert!(result.is_err());
+        let token = issuer.app_token(AUDIENCE).sign();
+
+        let result = map.validate_request(&validator, "app.molten.dev", "/healthz", None);
+        assert!(result.unwrap().is_none());
+
+        let result = map.validate_request(&validator, "app.molten.dev", "/", None);
+        assert!(result.is_err());
+
+        let result = map.validate_request(&validator, "app.molten.dev", "/admin", Some(&token));
+        assert!(result.is_err());
+
+        let result = map.validate_request(&validator, "app.molten.dev", "/healthz/../admin", None);
+        assert!(result.is_err());
+
+        let result =
+            map.validate_request(&validator, "app.molten.dev", "/admin%2F..%2Fhealthz", None);
+        assert!(result.is_err());
+
+        let result =
+            map.validate_request(&validator, "app.molten.dev", "/admin/%2e%2e/healthz", None);
+        assert!(result.is_err());
+    }
+}
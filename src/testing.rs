@@ -0,0 +1,376 @@
+use crate::{
+    api::TeamKeys,
+    clock::{Clock, SystemClock},
+    der, inspect,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use rsa::{pkcs1::EncodeRsaPrivateKey, rand_core::OsRng, traits::PublicKeyParts, RsaPrivateKey};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+const KEY_BITS: usize = 2048;
+const TOKEN_LIFETIME: u64 = 3600;
+
+struct MockKey {
+    key_id: String,
+    jwk: Value,
+    encoding_key: EncodingKey,
+}
+
+impl MockKey {
+    fn generate() -> Self {
+        let private_key = RsaPrivateKey::new(&mut OsRng, KEY_BITS).expect("RSA key generation");
+        let modulus = private_key.n().to_bytes_be();
+        let exponent = private_key.e().to_bytes_be();
+
+        // like CF, the kid is the SHA-256 fingerprint of the SubjectPublicKeyInfo
+        let spki = der::rsa_spki_der(&modulus, &exponent);
+        let key_id: String = Sha256::digest(spki)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+
+        let der = private_key.to_pkcs1_der().expect("PKCS#1 encoding");
+
+        MockKey {
+            jwk: json!({
+                "kid": key_id,
+                "kty": "RSA",
+                "alg": "RS256",
+                "use": "sig",
+                "e": URL_SAFE_NO_PAD.encode(&exponent),
+                "n": URL_SAFE_NO_PAD.encode(&modulus),
+            }),
+            key_id,
+            encoding_key: EncodingKey::from_rsa_der(der.as_bytes()),
+        }
+    }
+}
+
+/// Mints Cloudflare-shaped tokens for a mock team, signed with generated RSA keys,
+/// and publishes the matching keys in the `/cdn-cgi/access/certs` format.
+pub struct MockIssuer {
+    team_name: String,
+    keys: Vec<MockKey>,
+    latest_key_id: String,
+    clock: Arc<dyn Clock>,
+}
+
+impl MockIssuer {
+    /// Constructs a MockIssuer for a team with a single freshly generated key.
+    pub fn new(team_name: &str) -> Self {
+        let key = MockKey::generate();
+
+        MockIssuer {
+            team_name: team_name.to_string(),
+            latest_key_id: key.key_id.clone(),
+            keys: vec![key],
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Stamps the `iat`, `nbf` and `exp` claims of new tokens from the given Clock
+    /// instead of the system time, e.g. a FakeClock shared with a validator.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the team name of the issuer.
+    pub fn get_team_name(&self) -> String {
+        self.team_name.clone()
+    }
+
+    /// Returns the kid of the key used to sign new tokens.
+    pub fn get_latest_key_id(&self) -> String {
+        self.latest_key_id.clone()
+    }
+
+    /// Returns the kids of every published key, oldest first.
+    pub fn get_key_ids(&self) -> Vec<String> {
+        self.keys.iter().map(|key| key.key_id.clone()).collect()
+    }
+
+    /// Generates and publishes a new key without signing tokens with it yet.
+    /// Returns the kid of the new key.
+    pub fn publish_key(&mut self) -> String {
+        let key = MockKey::generate();
+        let key_id = key.key_id.clone();
+        self.keys.push(key);
+        key_id
+    }
+
+    /// Makes a published key the latest key, used to sign new tokens.
+    /// Returns whether the key is published.
+    pub fn promote(&mut self, key_id: &str) -> bool {
+        if !self.keys.iter().any(|key| key.key_id == key_id) {
+            return false;
+        }
+        self.latest_key_id = key_id.to_string();
+        true
+    }
+
+    /// Publishes and promotes a new key, keeping the previous keys published.
+    /// Returns the kid of the new key.
+    pub fn rotate(&mut self) -> String {
+        let key_id = self.publish_key();
+        self.promote(&key_id);
+        key_id
+    }
+
+    /// Stops publishing a previous key. The latest key cannot be retired.
+    /// Returns whether a key was retired.
+    pub fn retire(&mut self, key_id: &str) -> bool {
+        let len = self.keys.len();
+        self.keys
+            .retain(|key| key.key_id != key_id || key.key_id == self.latest_key_id);
+        self.keys.len() != len
+    }
+
+    /// Returns the published keys in the format of the CF API `/cdn-cgi/access/certs` endpoint.
+    pub fn to_certs_json(&self) -> Value {
+        let keys: Vec<Value> = self.keys.iter().map(|key| key.jwk.clone()).collect();
+        let public_certs: Vec<Value> = self
+            .keys
+            .iter()
+            .map(|key| json!({"kid": key.key_id}))
+            .collect();
+
+        json!({
+            "keys": keys,
+            "public_cert": {"kid": self.get_latest_key_id()},
+            "public_certs": public_certs,
+        })
+    }
+
+    /// Returns the published keys as TeamKeys.
+    pub fn to_team_keys(&self) -> TeamKeys {
+        TeamKeys::from_json(&self.team_name, self.to_certs_json()).unwrap()
+    }
+
+    fn get_common_claims(&self, audience: &str) -> Map<String, Value> {
+        let now = self.clock.timestamp();
+
+        let claims = json!({
+            "aud": [audience],
+            "iat": now,
+            "nbf": now,
+            "exp": now + TOKEN_LIFETIME,
+            "iss": inspect::get_team_issuer(&self.team_name),
+            "type": "app",
+        });
+        claims.as_object().unwrap().clone()
+    }
+
+    /// Begins an application token for a user, valid for an hour from the time of the issuer's Clock.
+    pub fn app_token(&self, audience: &str) -> MockToken<'_> {
+        let mut claims = self.get_common_claims(audience);
+        claims.insert("email".to_string(), json!("user@example.com"));
+        claims.insert(
+            "sub".to_string(),
+            json!("00000000-0000-0000-0000-000000000000"),
+        );
+        claims.insert("identity_nonce".to_string(), json!("mock-identity-nonce"));
+        claims.insert("country".to_string(), json!("AU"));
+        claims.insert("custom".to_string(), json!({}));
+
+        MockToken::new(self, claims)
+    }
+
+    /// Begins a token for a service token client, valid for an hour from the time of the issuer's Clock.
+    pub fn service_token(&self, audience: &str, client_id: &str) -> MockToken<'_> {
+        let mut claims = self.get_common_claims(audience);
+        claims.insert("common_name".to_string(), json!(client_id));
+        claims.insert("sub".to_string(), json!(""));
+        claims.insert("identity_nonce".to_string(), json!(""));
+
+        MockToken::new(self, claims)
+    }
+}
+
+/// A token being minted by a MockIssuer.
+pub struct MockToken<'a> {
+    issuer: &'a MockIssuer,
+    key_id: String,
+    claims: Map<String, Value>,
+}
+
+impl<'a> MockToken<'a> {
+    fn new(issuer: &'a MockIssuer, claims: Map<String, Value>) -> Self {
+        MockToken {
+            key_id: issuer.get_latest_key_id(),
+            issuer,
+            claims,
+        }
+    }
+
+    /// Sets a claim, replacing any existing value.
+    pub fn with_claim(mut self, key: &str, value: Value) -> Self {
+        self.claims.insert(key.to_string(), value);
+        self
+    }
+
+    /// Removes a claim.
+    pub fn without_claim(mut self, key: &str) -> Self {
+        self.claims.remove(key);
+        self
+    }
+
+    /// Sets the `iat` and `nbf` claims, and an `exp` claim an hour later.
+    pub fn with_issued_at(self, iat: u64) -> Self {
+        self.with_claim("iat", json!(iat))
+            .with_claim("nbf", json!(iat))
+            .with_claim("exp", json!(iat + TOKEN_LIFETIME))
+    }
+
+    /// Sets the `exp` claim.
+    pub fn with_expiry(self, exp: u64) -> Self {
+        self.with_claim("exp", json!(exp))
+    }
+
+    /// Sets the `nbf` claim.
+    pub fn with_not_before(self, nbf: u64) -> Self {
+        self.with_claim("nbf", json!(nbf))
+    }
+
+    /// Sets the kid in the token header. A kid the issuer does not publish
+    /// is signed with the latest key, to mint tokens for unknown keys.
+    pub fn with_key_id(mut self, key_id: &str) -> Self {
+        self.key_id = key_id.to_string();
+        self
+    }
+
+    /// Returns the claims of the token.
+    pub fn get_claims(&self) -> Value {
+        Value::Object(self.claims.clone())
+    }
+
+    /// Signs the token with RS256.
+    pub fn sign(&self) -> String {
+        let key = self
+            .issuer
+            .keys
+            .iter()
+            .find(|key| key.key_id == self.key_id)
+            .or_else(|| {
+                self.issuer
+                    .keys
+                    .iter()
+                    .find(|key| key.key_id == self.issuer.latest_key_id)
+            })
+            .unwrap();
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.key_id.clone());
+
+        jsonwebtoken::encode(&header, &self.claims, &key.encoding_key).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::ParseMode, app_token::ApplicationToken, clock::FakeClock, thumbprint, TeamValidator,
+        Validator,
+    };
+    use std::time::Duration;
+
+    const TEAM_NAME: &str = "molten";
+    const AUDIENCE: &str = "mock-audience";
+
+    fn get_constraints() -> jsonwebtoken::Validation {
+        let mut constraints = jsonwebtoken::Validation::new(Algorithm::RS256);
+        constraints.set_audience(&[AUDIENCE]);
+        constraints.set_issuer(&[inspect::get_team_issuer(TEAM_NAME)]);
+        constraints
+    }
+
+    #[test]
+    fn test_mock_issuer() {
+        let mut issuer = MockIssuer::new(TEAM_NAME);
+        let mut team_keys =
+            TeamKeys::from_str(TEAM_NAME, &issuer.to_certs_json().to_string()).unwrap();
+        assert_eq!(team_keys.latest_key_id, issuer.get_latest_key_id());
+        assert!(team_keys.verify_key_ids(ParseMode::Strict).is_ok());
+        assert!(team_keys
+            .keys
+            .values()
+            .all(|key| thumbprint::key_id_matches(key.as_ref()) == Some(true)));
+
+        let validator = TeamValidator::from_team_keys(team_keys);
+        let token = issuer
+            .app_token(AUDIENCE)
+            .with_claim("email", json!("someone@example.com"))
+            .sign();
+        let token_data = validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .unwrap();
+        let app_token = ApplicationToken::from_token_data(token_data).unwrap();
+        assert_eq!(app_token.email, "someone@example.com");
+
+        // tokens signed with a new key validate once the key is synced
+        let previous_key_id = issuer.get_latest_key_id();
+        let latest_key_id = issuer.rotate();
+        assert_eq!(issuer.get_key_ids().len(), 2);
+        assert!(!issuer.retire(&latest_key_id));
+        assert!(issuer.retire(&previous_key_id));
+        assert_eq!(issuer.get_key_ids(), vec![latest_key_id]);
+
+        let token = issuer.service_token(AUDIENCE, "client.access").sign();
+        assert!(validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .is_err());
+        assert!(validator.update_keys(issuer.to_team_keys()));
+        assert!(validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_mock_token_times() {
+        const NOW: u64 = 1717979639;
+
+        let clock = Arc::new(FakeClock::new(NOW));
+        let issuer = MockIssuer::new(TEAM_NAME).with_clock(clock.clone());
+        let validator =
+            TeamValidator::from_team_keys(issuer.to_team_keys()).with_clock(clock.clone());
+
+        let token = issuer.app_token(AUDIENCE);
+        assert_eq!(token.get_claims()["iat"], json!(NOW));
+        assert_eq!(token.get_claims()["exp"], json!(NOW + TOKEN_LIFETIME));
+        let token = token.sign();
+        assert!(validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .is_ok());
+
+        // beyond the default leeway of 60 seconds
+        clock.advance(Duration::from_secs(TOKEN_LIFETIME + 61));
+        assert!(validator
+            .validate_token(&token, TEAM_NAME, &mut get_constraints())
+            .is_err());
+
+        let expired = issuer.app_token(AUDIENCE).with_issued_at(NOW - 7200).sign();
+        assert!(validator
+            .validate_token(&expired, TEAM_NAME, &mut get_constraints())
+            .is_err());
+
+        let early = issuer
+            .app_token(AUDIENCE)
+            .with_not_before(clock.timestamp() + 600)
+            .sign();
+        let mut constraints = get_constraints();
+        constraints.validate_nbf = true;
+        assert!(validator
+            .validate_token(&early, TEAM_NAME, &mut constraints)
+            .is_err());
+
+        let unknown = issuer.app_token(AUDIENCE).with_key_id("unknown").sign();
+        assert!(validator
+            .validate_token(&unknown, TEAM_NAME, &mut get_constraints())
+            .is_err());
+    }
+}
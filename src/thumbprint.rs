@@ -0,0 +1,99 @@
+use crate::{
+    der::{decode_b64, rsa_spki_der},
+    keys::AccessKey,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::jwk;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, b| {
+        let _ = write!(out, "{b:02x}");
+        out
+    })
+}
+
+/// Computes the RFC 7638 SHA-256 thumbprint of an RSA JWK.
+pub fn rsa_thumbprint(params: &jwk::RSAKeyParameters) -> Vec<u8> {
+    // members in lexicographic order, no whitespace
+    let canonical = format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, params.e, params.n);
+    Sha256::digest(canonical.as_bytes()).to_vec()
+}
+
+/// Computes the SHA-256 fingerprint of the DER SubjectPublicKeyInfo of an RSA JWK.
+/// This is the derivation Cloudflare uses for the kids of its signing keys.
+pub fn rsa_spki_fingerprint(params: &jwk::RSAKeyParameters) -> Option<Vec<u8>> {
+    let modulus = decode_b64(&params.n)?;
+    let exponent = decode_b64(&params.e)?;
+    Some(Sha256::digest(rsa_spki_der(&modulus, &exponent)).to_vec())
+}
+
+/// Returns the encodings of an RSA key's thumbprints that may be pinned:
+/// the RFC 7638 thumbprint (hex and base64url) and the hex SHA-256 fingerprint
+/// of its SubjectPublicKeyInfo. Returns `None` for non-RSA keys.
+pub fn get_thumbprints(access_key: &dyn AccessKey) -> Option<Vec<String>> {
+    let params = match access_key.get_jwk().algorithm {
+        jwk::AlgorithmParameters::RSA(params) => params,
+        _ => return None,
+    };
+
+    let thumbprint = rsa_thumbprint(&params);
+    let mut thumbprints = vec![to_hex(&thumbprint), URL_SAFE_NO_PAD.encode(&thumbprint)];
+
+    if let Some(fingerprint) = rsa_spki_fingerprint(&params) {
+        thumbprints.push(to_hex(&fingerprint));
+    }
+
+    Some(thumbprints)
+}
+
+/// Returns whether the kid of an AccessKey is one of its thumbprints, see `get_thumbprints`.
+/// Hex encodings are compared case-insensitively. Returns `None` for non-RSA keys.
+pub fn key_id_matches(access_key: &dyn AccessKey) -> Option<bool> {
+    let key_id = access_key.get_key_id();
+    let thumbprints = get_thumbprints(access_key)?;
+    Some(
+        thumbprints
+            .iter()
+            .any(|thumbprint| *thumbprint == key_id || *thumbprint == key_id.to_ascii_lowercase()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::RsaAccessKey;
+
+    // RFC 7638 section 3.1
+    const RFC_KEY_ID: &str = "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs";
+    const RFC_N: &str = "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw";
+    const RFC_E: &str = "AQAB";
+
+    #[test]
+    fn test_rfc7638_thumbprint() {
+        let key = RsaAccessKey::new(RFC_KEY_ID, "RS256", "sig", RFC_E, RFC_N);
+        assert!(get_thumbprints(&key)
+            .unwrap()
+            .contains(&RFC_KEY_ID.to_string()));
+    }
+
+    #[test]
+    fn test_key_id_matches() {
+        let key = RsaAccessKey::new(RFC_KEY_ID, "RS256", "sig", RFC_E, RFC_N);
+        assert_eq!(key_id_matches(&key), Some(true));
+
+        for thumbprint in get_thumbprints(&key).unwrap() {
+            let key = RsaAccessKey::new(&thumbprint, "RS256", "sig", RFC_E, RFC_N);
+            assert_eq!(key_id_matches(&key), Some(true));
+        }
+
+        let fingerprint = get_thumbprints(&key).unwrap().pop().unwrap();
+        let key = RsaAccessKey::new(&fingerprint.to_uppercase(), "RS256", "sig", RFC_E, RFC_N);
+        assert_eq!(key_id_matches(&key), Some(true));
+
+        let key = RsaAccessKey::new("tampered", "RS256", "sig", RFC_E, RFC_N);
+        assert_eq!(key_id_matches(&key), Some(false));
+    }
+}
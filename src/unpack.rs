@@ -41,7 +41,7 @@ pub fn get_key<'a>(obj: &'a JsonObject, key: &str) -> UnpackResult<&'a Value> {
 }
 
 pub fn as_object_get_key<'a>(val: &'a Value, key: &str) -> UnpackResult<&'a Value> {
-    Ok(get_key(as_object(val)?, key)?)
+    get_key(as_object(val)?, key)
 }
 
 #[cfg(test)]